@@ -0,0 +1,373 @@
+//! Album art rendering
+//!
+//! Kitty, iTerm2 and sixel images are written straight to the terminal after the frame is drawn.
+//! Other terminals get a half-block fallback drawn into the buffer, which needs truecolor.
+use gonk_core::art::Art;
+use std::{
+    env,
+    io::{self, Write},
+    sync::Arc,
+};
+use winter::*;
+
+//Sixel images are sized in pixels, this is a common cell size.
+const CELL_WIDTH: u16 = 10;
+const CELL_HEIGHT: u16 = 20;
+
+//Kitty limits each escape sequence to 4096 bytes of data.
+const CHUNK: usize = 4096;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Protocol {
+    Kitty,
+    Iterm,
+    Sixel,
+    HalfBlock,
+}
+
+///Guess the best image protocol from the environment.
+///
+///Windows Terminal only supports sixel since 1.22 and doesn't say which version
+///it is, so it gets half blocks.
+pub fn detect() -> Protocol {
+    let var = |key| env::var(key).unwrap_or_default();
+    let term = var("TERM");
+    let program = var("TERM_PROGRAM");
+
+    if env::var_os("KITTY_WINDOW_ID").is_some() || term == "xterm-kitty" || program == "ghostty" {
+        Protocol::Kitty
+    } else if program == "iTerm.app" || program == "WezTerm" || var("LC_TERMINAL") == "iTerm2" {
+        Protocol::Iterm
+    } else if term.starts_with("foot") || term.starts_with("mlterm") || term.contains("sixel") {
+        Protocol::Sixel
+    } else {
+        Protocol::HalfBlock
+    }
+}
+
+pub struct Cover {
+    pub protocol: Protocol,
+    pub enabled: bool,
+    ///Drawing failed, art stays off until gonk is restarted.
+    pub failed: bool,
+    //Where the image should be this frame.
+    pending: Option<(Arc<Art>, Rect)>,
+    //What's on screen right now.
+    shown: Option<(Arc<Art>, Rect)>,
+    viewport: Rect,
+}
+
+impl Cover {
+    pub fn new() -> Self {
+        Self {
+            protocol: detect(),
+            enabled: true,
+            failed: false,
+            pending: None,
+            shown: None,
+            viewport: Rect::default(),
+        }
+    }
+}
+
+///The largest area with the same aspect ratio as the art, centered in `area`.
+///Cells are assumed to be twice as tall as they are wide.
+pub fn fit(art: &Art, area: Rect) -> Rect {
+    if art.width == 0 || art.height == 0 {
+        return Rect::default();
+    }
+    let (width, height) = (art.width as u64, art.height as u64);
+    let (columns, rows) = (area.width as u64, area.height as u64 * 2);
+    let (w, h) = if columns * height <= rows * width {
+        (columns, columns * height / width)
+    } else {
+        (rows * width / height, rows)
+    };
+    let (w, h) = (w as u16, (h / 2) as u16);
+
+    Rect {
+        x: area.x + (area.width - w) / 2,
+        y: area.y + (area.height - h) / 2,
+        width: w,
+        height: h,
+    }
+}
+
+///Queue the art to be drawn inside `area`.
+pub fn draw(cover: &mut Cover, art: &Arc<Art>, area: Rect, buf: &mut Buffer) {
+    let rect = fit(art, area);
+    if rect.width == 0 || rect.height == 0 {
+        return;
+    }
+
+    if cover.protocol == Protocol::HalfBlock {
+        half_block(art, rect, buf);
+    } else {
+        //The image covers these cells, keep them empty.
+        buf.clear(rect);
+        cover.pending = Some((art.clone(), rect));
+    }
+}
+
+//Nearest neighbour, `x` and `y` are in a `width` by `height` grid.
+fn sample(art: &Art, x: u32, y: u32, width: u32, height: u32) -> [u8; 3] {
+    let x = (x * art.width / width).min(art.width - 1);
+    let y = (y * art.height / height).min(art.height - 1);
+    let i = (y * art.width + x) as usize * 3;
+    [art.rgb[i], art.rgb[i + 1], art.rgb[i + 2]]
+}
+
+//Each cell holds two pixels, the top is the foreground and the bottom is the background.
+fn half_block(art: &Art, rect: Rect, buf: &mut Buffer) {
+    let (width, height) = (rect.width as u32, rect.height as u32 * 2);
+    for row in 0..rect.height {
+        for column in 0..rect.width {
+            let [r, g, b] = sample(art, column as u32, row as u32 * 2, width, height);
+            let top = Color::Rgb(r, g, b);
+            let [r, g, b] = sample(art, column as u32, row as u32 * 2 + 1, width, height);
+            let bottom = Color::Rgb(r, g, b);
+
+            let area = Rect {
+                x: rect.x + column,
+                y: rect.y + row,
+                width: 1,
+                height: 1,
+            };
+            lines!("▀".fg(top).bg(bottom)).draw(area, buf);
+        }
+    }
+}
+
+///Remove the image if it moved or changed. Call this before the frame is drawn.
+pub fn erase<W: Write>(cover: &mut Cover, stdout: &mut W, viewport: Rect) -> io::Result<()> {
+    let changed = match (&cover.pending, &cover.shown) {
+        (Some((art, rect)), Some((shown, shown_rect))) => {
+            !Arc::ptr_eq(art, shown) || rect != shown_rect
+        }
+        (None, Some(_)) => true,
+        _ => false,
+    };
+
+    //Resizing redraws the whole screen.
+    if changed || viewport != cover.viewport {
+        if let Some((_, rect)) = cover.shown.take() {
+            if cover.protocol == Protocol::Kitty {
+                write!(stdout, "\x1b_Ga=d,d=A,q=2\x1b\\")?;
+            } else {
+                //Writing over an image removes it. The buffer is already empty here.
+                let blank = " ".repeat(rect.width as usize);
+                for y in rect.y..rect.y + rect.height {
+                    goto(stdout, rect.x, y)?;
+                    write!(stdout, "{blank}")?;
+                }
+            }
+        }
+        cover.viewport = viewport;
+    }
+
+    Ok(())
+}
+
+///Write the image to the terminal. Call this after the frame is drawn.
+pub fn present<W: Write>(cover: &mut Cover, stdout: &mut W) -> io::Result<()> {
+    //The art has to be drawn again every frame to stay on screen.
+    let Some((art, rect)) = cover.pending.take() else {
+        return Ok(());
+    };
+    if cover.shown.is_some() {
+        return Ok(());
+    }
+
+    goto(stdout, rect.x, rect.y)?;
+    match cover.protocol {
+        Protocol::Kitty => kitty(stdout, &art, rect)?,
+        Protocol::Iterm => iterm(stdout, &art, rect)?,
+        Protocol::Sixel => sixel(stdout, &art, rect)?,
+        Protocol::HalfBlock => return Ok(()),
+    }
+    cover.shown = Some((art, rect));
+    Ok(())
+}
+
+fn goto<W: Write>(stdout: &mut W, x: u16, y: u16) -> io::Result<()> {
+    write!(stdout, "\x1b[{};{}H", y + 1, x + 1)
+}
+
+fn base64(input: &[u8]) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut output = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                output.push(TABLE[(n >> (18 - i * 6)) as usize & 63] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
+
+//https://sw.kovidgoyal.net/kitty/graphics-protocol/
+fn kitty<W: Write>(stdout: &mut W, art: &Art, rect: Rect) -> io::Result<()> {
+    let data = base64(&art.png);
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(CHUNK).collect();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = (i + 1 < chunks.len()) as u8;
+        if i == 0 {
+            write!(
+                stdout,
+                "\x1b_Ga=T,f=100,q=2,C=1,c={},r={},m={more};",
+                rect.width, rect.height
+            )?;
+        } else {
+            write!(stdout, "\x1b_Gm={more};")?;
+        }
+        stdout.write_all(chunk)?;
+        write!(stdout, "\x1b\\")?;
+    }
+    Ok(())
+}
+
+//https://iterm2.com/documentation-images.html
+fn iterm<W: Write>(stdout: &mut W, art: &Art, rect: Rect) -> io::Result<()> {
+    write!(
+        stdout,
+        "\x1b]1337;File=inline=1;size={};width={};height={};preserveAspectRatio=1:{}\x07",
+        art.png.len(),
+        rect.width,
+        rect.height,
+        base64(&art.png)
+    )
+}
+
+//Reduce the image to the 6x6x6 colour cube.
+fn quantize([r, g, b]: [u8; 3]) -> usize {
+    let level = |c: u8| (c as usize * 5 + 127) / 255;
+    level(r) * 36 + level(g) * 6 + level(b)
+}
+
+///Encode the art as a sixel image filling `rect`.
+fn encode_sixel(art: &Art, rect: Rect) -> String {
+    let width = (rect.width * CELL_WIDTH) as u32;
+    let height = (rect.height * CELL_HEIGHT) as u32;
+    let pixels: Vec<usize> = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| quantize(sample(art, x, y, width, height)))
+        .collect();
+
+    let mut out = format!("\x1bP0;1;0q\"1;1;{width};{height}");
+    for i in 0..216 {
+        let percent = |level: usize| level * 100 / 5;
+        out.push_str(&format!(
+            "#{i};2;{};{};{}",
+            percent(i / 36),
+            percent(i / 6 % 6),
+            percent(i % 6)
+        ));
+    }
+
+    //Each band is six pixels tall.
+    for band in (0..height as usize).step_by(6) {
+        let rows = 6.min(height as usize - band);
+        let mut colors: Vec<usize> = (band..band + rows)
+            .flat_map(|y| &pixels[y * width as usize..(y + 1) * width as usize])
+            .copied()
+            .collect();
+        colors.sort_unstable();
+        colors.dedup();
+
+        for (n, color) in colors.iter().enumerate() {
+            if n > 0 {
+                out.push('$');
+            }
+            out.push_str(&format!("#{color}"));
+
+            //Repeated characters are run length encoded.
+            let mut run: Option<(char, usize)> = None;
+            for x in 0..width as usize {
+                let bits = (0..rows)
+                    .filter(|row| pixels[(band + row) * width as usize + x] == *color)
+                    .fold(0, |bits, row| bits | 1 << row);
+                let c = (63 + bits) as u8 as char;
+                run = match run {
+                    Some((prev, count)) if prev == c => Some((c, count + 1)),
+                    Some(prev) => {
+                        push_run(&mut out, prev);
+                        Some((c, 1))
+                    }
+                    None => Some((c, 1)),
+                };
+            }
+            if let Some(run) = run {
+                push_run(&mut out, run);
+            }
+        }
+        out.push('-');
+    }
+
+    out.push_str("\x1b\\");
+    out
+}
+
+fn push_run(out: &mut String, (c, count): (char, usize)) {
+    if count > 3 {
+        out.push_str(&format!("!{count}{c}"));
+    } else {
+        out.extend(std::iter::repeat_n(c, count));
+    }
+}
+
+//https://vt100.net/docs/vt3xx-gp/chapter14.html
+fn sixel<W: Write>(stdout: &mut W, art: &Art, rect: Rect) -> io::Result<()> {
+    stdout.write_all(encode_sixel(art, rect).as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn white(width: u32, height: u32) -> Art {
+        Art {
+            width,
+            height,
+            rgb: vec![255; (width * height * 3) as usize],
+            png: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn art() {
+        let area = Rect {
+            x: 2,
+            y: 1,
+            width: 40,
+            height: 10,
+        };
+        let rect = fit(&white(256, 256), area);
+        assert_eq!((rect.x, rect.y, rect.width, rect.height), (12, 1, 20, 10));
+        let rect = fit(&white(256, 128), area);
+        assert_eq!((rect.x, rect.y, rect.width, rect.height), (2, 1, 40, 10));
+
+        assert_eq!(base64(b"gonk"), "Z29uaw==");
+        assert_eq!(base64(b"gon"), "Z29u");
+
+        let sixel = encode_sixel(
+            &white(2, 2),
+            Rect {
+                width: 1,
+                height: 1,
+                ..Default::default()
+            },
+        );
+        assert!(sixel.starts_with("\x1bP0;1;0q\"1;1;10;20"));
+        assert!(sixel.contains("#215!10~-"));
+        assert!(sixel.ends_with("-\x1b\\"));
+    }
+}
@@ -1,5 +1,7 @@
-use gonk_core::{vdb::Database, Album};
+use crate::art::{self, Cover};
+use gonk_core::{art::Art, format_duration, vdb::Database, Album};
 use gonk_core::{Index, Song};
+use std::{sync::Arc, time::Duration};
 use winter::*;
 
 #[derive(PartialEq, Eq)]
@@ -13,7 +15,7 @@ pub struct Browser {
     artists: Index<String>,
     albums: Index<Album>,
     ///Title, (disc, track)
-    songs: Index<(String, (u8, u8))>,
+    songs: Index<(String, (u16, u16))>,
     pub mode: Mode,
 }
 
@@ -36,7 +38,7 @@ impl Browser {
                                 (song.disc_number, song.track_number),
                             )
                         })
-                        .collect::<Vec<(String, (u8, u8))>>(),
+                        .collect::<Vec<(String, (u16, u16))>>(),
                 );
             }
         }
@@ -89,6 +91,8 @@ pub fn draw(
     area: winter::Rect,
     buf: &mut winter::Buffer,
     mouse: Option<(u16, u16)>,
+    cover: &mut Cover,
+    art: Option<Arc<Art>>,
 ) {
     let size = area.width / 3;
     let rem = area.width % 3;
@@ -119,7 +123,14 @@ pub fn draw(
     }
 
     let artists: Vec<_> = browser.artists.iter().map(|a| lines!(a)).collect();
-    let albums: Vec<_> = browser.albums.iter().map(|a| lines!(&a.title)).collect();
+    let albums: Vec<_> = browser
+        .albums
+        .iter()
+        .map(|a| {
+            let total: Duration = a.songs.iter().map(|song| song.duration).sum();
+            lines!(&a.title, text!(" {}", format_duration(total)).dim())
+        })
+        .collect();
     let songs: Vec<_> = browser.songs.iter().map(|(s, _)| lines!(s)).collect();
 
     fn list<'a>(title: &'static str, items: Vec<Line<'a>>, use_symbol: bool) -> List<'a> {
@@ -132,11 +143,39 @@ pub fn draw(
     let albums = list("Album", albums, browser.mode == Mode::Album);
     let songs = list("Song", songs, browser.mode == Mode::Song);
 
+    //The cover takes up to half of the album column.
+    let mut album_area = chunks[1];
+    if let Some(art) = art.filter(|_| cover.enabled) {
+        let height = (album_area.width / 2).min(album_area.height / 2);
+        if height >= 8 {
+            let split = layout(
+                album_area,
+                Direction::Vertical,
+                &[
+                    Constraint::Length(album_area.height - height),
+                    Constraint::Length(height),
+                ],
+            );
+            block().draw(split[1], buf);
+            if let Ok(inner) = split[1].inner(1, 1) {
+                art::draw(cover, &art, inner, buf);
+            }
+            album_area = split[0];
+        }
+    }
+
     artists.draw(chunks[0], buf, browser.artists.index());
-    albums.draw(chunks[1], buf, browser.albums.index());
+    albums.draw(album_area, buf, browser.albums.index());
     songs.draw(chunks[2], buf, browser.songs.index());
 }
 
+///The artist and title of the selected album.
+pub fn selected_album(browser: &Browser) -> Option<(&str, &str)> {
+    let artist = browser.artists.selected()?;
+    let album = browser.albums.selected()?;
+    Some((artist, &album.title))
+}
+
 pub fn refresh(browser: &mut Browser, db: &Database) {
     browser.mode = Mode::Artist;
 
@@ -166,7 +205,7 @@ pub fn update_albums(browser: &mut Browser, db: &Database) {
 pub fn update_songs(browser: &mut Browser, db: &Database) {
     if let Some(artist) = browser.artists.selected() {
         if let Some(album) = browser.albums.selected() {
-            let songs: Vec<(String, (u8, u8))> = db
+            let songs: Vec<(String, (u16, u16))> = db
                 .album(artist, &album.title)
                 .songs
                 .iter()
@@ -0,0 +1,149 @@
+use crate::{NUMBER, TITLE};
+use gonk_core::{
+    duplicates::{describe, find},
+    format_duration,
+    vdb::Database,
+    Index, Song,
+};
+use winter::*;
+
+pub enum Entry {
+    ///Artist, album and title of the group.
+    Group(String, String, String),
+    Song(Song),
+}
+
+pub struct Duplicates {
+    pub entries: Index<Entry>,
+    pub groups: usize,
+}
+
+impl Duplicates {
+    pub fn new() -> Self {
+        Self {
+            entries: Index::default(),
+            groups: 0,
+        }
+    }
+}
+
+pub fn refresh(duplicates: &mut Duplicates, db: &Database) {
+    let songs: Vec<Song> = db
+        .artists()
+        .into_iter()
+        .flat_map(|artist| db.albums_by_artist(artist))
+        .flat_map(|album| album.songs.iter().cloned())
+        .collect();
+    let groups = find(&songs);
+
+    let mut entries = Vec::new();
+    for group in &groups {
+        let song = &group[0];
+        entries.push(Entry::Group(
+            song.artist.clone(),
+            song.album.clone(),
+            song.title.clone(),
+        ));
+        entries.extend(group.iter().cloned().map(Entry::Song));
+    }
+
+    //The first entry is always a group.
+    let index = (!entries.is_empty()).then_some(1);
+    duplicates.entries = Index::new(entries, index);
+    duplicates.groups = groups.len();
+}
+
+//Groups can't be selected.
+fn step(duplicates: &mut Duplicates, amount: usize, up: bool) {
+    let Some(mut index) = duplicates.entries.index() else {
+        return;
+    };
+    let len = duplicates.entries.len();
+    for _ in 0..amount {
+        loop {
+            index = if up {
+                gonk_core::up(len, index, 1)
+            } else {
+                gonk_core::down(len, index, 1)
+            };
+            if let Entry::Song(_) = duplicates.entries[index] {
+                break;
+            }
+        }
+    }
+    duplicates.entries.select(Some(index));
+}
+
+pub fn up(duplicates: &mut Duplicates, amount: usize) {
+    step(duplicates, amount, true);
+}
+
+pub fn down(duplicates: &mut Duplicates, amount: usize) {
+    step(duplicates, amount, false);
+}
+
+pub fn selected(duplicates: &Duplicates) -> Option<&Song> {
+    match duplicates.entries.selected()? {
+        Entry::Song(song) => Some(song),
+        Entry::Group(..) => None,
+    }
+}
+
+pub fn draw(duplicates: &Duplicates, area: Rect, buf: &mut Buffer) {
+    let title = match duplicates.groups {
+        0 => "Duplicates".to_string(),
+        1 => "Duplicates ─ 1 group".to_string(),
+        n => format!("Duplicates ─ {n} groups"),
+    };
+    let block = block().title(title.as_str()).title_margin(1);
+
+    if duplicates.entries.is_empty() {
+        return lines!("No duplicates found.".dim())
+            .block(block)
+            .draw(area, buf);
+    }
+
+    let index = duplicates.entries.index();
+    let rows: Vec<Row> = duplicates
+        .entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| match entry {
+            Entry::Group(artist, album, title) => row![
+                text!(),
+                format!("{artist} ─ {album} ─ {title}").fg(TITLE).bold(),
+                text!(),
+                text!()
+            ],
+            Entry::Song(song) => {
+                let selected = if index == Some(i) { ">" } else { "" };
+                row![
+                    selected,
+                    song.path.as_str(),
+                    describe(song).fg(NUMBER),
+                    format_duration(song.duration).fg(NUMBER)
+                ]
+            }
+        })
+        .collect();
+
+    let table = table(
+        rows,
+        &[
+            Constraint::Length(1),
+            Constraint::Percentage(65),
+            Constraint::Percentage(35),
+            Constraint::Length(7),
+        ],
+    )
+    .header(header![
+        text!(),
+        "Path".bold(),
+        "Format".bold(),
+        "Time".bold()
+    ])
+    .block(block)
+    .spacing(1);
+
+    table.draw(area, buf, index);
+}
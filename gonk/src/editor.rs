@@ -0,0 +1,169 @@
+use gonk_core::{edit::Field, Song};
+use std::path::PathBuf;
+use winter::*;
+
+const SONG: [Field; 8] = [
+    Field::Title,
+    Field::TrackArtist,
+    Field::Artist,
+    Field::Album,
+    Field::TrackNumber,
+    Field::DiscNumber,
+    Field::Year,
+    Field::Genre,
+];
+const ALBUM: [Field; 4] = [Field::Artist, Field::Album, Field::Year, Field::Genre];
+const ARTIST: [Field; 1] = [Field::Artist];
+
+const LABEL_WIDTH: u16 = 14;
+
+pub enum Selection {
+    Song,
+    Album,
+    Artist,
+}
+
+pub struct Editor {
+    pub songs: Vec<Song>,
+    pub fields: &'static [Field],
+    pub values: Vec<String>,
+    pub index: usize,
+    title: &'static str,
+}
+
+impl Editor {
+    pub fn new(songs: Vec<Song>, selection: Selection) -> Option<Self> {
+        let (fields, title): (&'static [Field], _) = match selection {
+            Selection::Song => (&SONG, "Edit song"),
+            Selection::Album => (&ALBUM, "Edit album"),
+            Selection::Artist => (&ARTIST, "Edit artist"),
+        };
+        //Every song starts with the values of the first.
+        let first = songs.first()?;
+        Some(Self {
+            values: fields.iter().map(|field| field.value(first)).collect(),
+            songs,
+            fields,
+            index: 0,
+            title,
+        })
+    }
+}
+
+pub fn up(editor: &mut Editor) {
+    editor.index = gonk_core::up(editor.fields.len(), editor.index, 1);
+}
+
+pub fn down(editor: &mut Editor) {
+    editor.index = gonk_core::down(editor.fields.len(), editor.index, 1);
+}
+
+pub fn on_char(editor: &mut Editor, c: char) {
+    editor.values[editor.index].push(c);
+}
+
+pub fn on_backspace(editor: &mut Editor, control: bool) {
+    let value = &mut editor.values[editor.index];
+    if control {
+        let trim = value.trim_end();
+        let end = trim.rfind(' ').map_or(0, |i| i + 1);
+        value.truncate(end);
+    } else {
+        value.pop();
+    }
+}
+
+///The fields that were changed for each song.
+pub fn changes(editor: &Editor) -> Vec<(PathBuf, Vec<(Field, String)>)> {
+    let first = &editor.songs[0];
+    let changed: Vec<(Field, String)> = editor
+        .fields
+        .iter()
+        .zip(&editor.values)
+        .filter(|(field, value)| field.value(first) != **value)
+        .map(|(field, value)| (*field, value.clone()))
+        .collect();
+
+    if changed.is_empty() {
+        return Vec::new();
+    }
+
+    editor
+        .songs
+        .iter()
+        .map(|song| {
+            let mut changes = changed.clone();
+
+            //Fixing the album artist should also fix the track artist if they're the same.
+            let artist = changed.iter().find(|(field, _)| *field == Field::Artist);
+            if let Some((_, artist)) = artist {
+                if !editor.fields.contains(&Field::TrackArtist) && song.track_artist == song.artist
+                {
+                    changes.push((Field::TrackArtist, artist.clone()));
+                }
+            }
+
+            (PathBuf::from(&song.path), changes)
+        })
+        .collect()
+}
+
+//Only the end of long values is shown.
+fn tail(value: &str, width: usize) -> &str {
+    let mut start = 0;
+    while value[start..].width() > width {
+        start += value[start..].chars().next().map_or(1, char::len_utf8);
+    }
+    &value[start..]
+}
+
+///Returns the position of the cursor.
+pub fn draw(editor: &Editor, area: Rect, buf: &mut Buffer) -> Option<(u16, u16)> {
+    let height = editor.fields.len() as u16 + 4;
+    let Ok(area) = area.centered(60, height) else {
+        return None;
+    };
+    buf.clear(area);
+
+    let title = if editor.songs.len() == 1 {
+        editor.title.to_string()
+    } else {
+        format!("{} ─ {} songs", editor.title, editor.songs.len())
+    };
+    block()
+        .title(title.as_str())
+        .title_margin(1)
+        .draw(area, buf);
+
+    let Ok(inner) = area.inner(2, 1) else {
+        return None;
+    };
+    let width = inner.width.saturating_sub(LABEL_WIDTH + 1) as usize;
+    let mut cursor = None;
+
+    for (i, (field, value)) in editor.fields.iter().zip(&editor.values).enumerate() {
+        let rect = Rect {
+            y: inner.y + i as u16,
+            height: 1,
+            ..inner
+        };
+        let value = tail(value, width);
+        let mut line = lines!(text!("{:<14}", field.label()), value);
+        if i == editor.index {
+            line.style = Some(fg(Black).bg(White));
+            cursor = Some((rect.x + LABEL_WIDTH + value.width() as u16, rect.y));
+        }
+        line.draw(rect, buf);
+    }
+
+    let help = Rect {
+        y: inner.y + inner.height.saturating_sub(1),
+        height: 1,
+        ..inner
+    };
+    lines!("Enter to save, Escape to cancel".dim())
+        .align(Center)
+        .draw(help, buf);
+
+    cursor
+}
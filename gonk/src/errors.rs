@@ -0,0 +1,118 @@
+use crate::NUMBER;
+use gonk_core::{
+    db::{self, ScanError},
+    settings::Settings as Persist,
+    walk, Index,
+};
+use std::path::Path;
+use winter::*;
+
+pub struct Errors {
+    pub errors: Index<ScanError>,
+}
+
+impl Errors {
+    pub fn new() -> Self {
+        let mut errors = Self {
+            errors: Index::default(),
+        };
+        refresh(&mut errors);
+        errors
+    }
+}
+
+///Read the report again, it's updated by every scan.
+pub fn refresh(errors: &mut Errors) {
+    let report = db::read_errors();
+    let index = match errors.errors.index() {
+        _ if report.is_empty() => None,
+        Some(i) => Some(i.min(report.len() - 1)),
+        None => Some(0),
+    };
+    errors.errors = Index::new(report, index);
+}
+
+pub fn up(errors: &mut Errors, amount: usize) {
+    errors.errors.up_n(amount);
+}
+
+pub fn down(errors: &mut Errors, amount: usize) {
+    errors.errors.down_n(amount);
+}
+
+///Exclude the selected file from the library and remove it from the report.
+pub fn ignore(errors: &mut Errors, persist: &mut Persist) -> Option<String> {
+    let error = errors.errors.selected()?;
+    let path = Path::new(&error.path);
+    let folder = persist
+        .music_folders
+        .iter()
+        .find(|folder| path.starts_with(folder))?;
+
+    let relative = path.strip_prefix(folder).ok()?;
+    let pattern = walk::anchored(relative);
+    if !persist.ignore.contains(&pattern) {
+        persist.ignore.push(pattern.clone());
+    }
+
+    let i = errors.errors.index()?;
+    errors.errors.remove(i);
+    if errors.errors.is_empty() {
+        errors.errors.select(None);
+    } else if i >= errors.errors.len() {
+        errors.errors.select(Some(errors.errors.len() - 1));
+    }
+    if let Err(e) = db::write_errors(&errors.errors) {
+        gonk_core::log!("Failed to update the error report! {e}");
+    }
+
+    Some(pattern)
+}
+
+pub fn draw(errors: &Errors, area: Rect, buf: &mut Buffer) {
+    let title = match errors.errors.len() {
+        0 => "Scan errors".to_string(),
+        len => format!("Scan errors ─ {len}"),
+    };
+    let block = block().title(title.as_str()).title_margin(1);
+
+    if errors.errors.is_empty() {
+        return lines!("No scan errors.".dim()).block(block).draw(area, buf);
+    }
+
+    let index = errors.errors.index();
+    let rows: Vec<Row> = errors
+        .errors
+        .iter()
+        .enumerate()
+        .map(|(i, error)| {
+            let selected = if index == Some(i) { ">" } else { "" };
+            row![
+                selected,
+                error.kind.label().fg(NUMBER),
+                error.path.as_str(),
+                error.message.as_str().dim()
+            ]
+        })
+        .collect();
+
+    let table = table(
+        rows,
+        &[
+            Constraint::Length(1),
+            Constraint::Length(11),
+            Constraint::Percentage(60),
+            Constraint::Percentage(40),
+        ],
+    )
+    .header(header![
+        text!(),
+        "Error".bold(),
+        "Path".bold(),
+        "Message".bold()
+    ])
+    .block(block)
+    .spacing(1);
+
+    table.draw(area, buf, index);
+}
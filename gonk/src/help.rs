@@ -5,7 +5,7 @@ use winter::*;
 //TODO: Add scrolling to the help menu.
 //TODO: Improve visability, it's hard to tell which option matches which command.
 //TODO: Do I have a widget for adding lines?
-pub static HELP: LazyLock<[Row; 32]> = LazyLock::new(|| {
+pub static HELP: LazyLock<[Row; 39]> = LazyLock::new(|| {
     [
         row!["Move Up".fg(Cyan), "K / UP"],
         row!["Move Down".fg(Cyan), "J / Down"],
@@ -24,10 +24,15 @@ pub static HELP: LazyLock<[Row; 32]> = LazyLock::new(|| {
         row!["Next".fg(Magenta), "D"],
         row!["Seek -10s".fg(Magenta), "Q"],
         row!["Seek 10s".fg(Magenta), "E"],
+        row!["Sleep timer".fg(Magenta), "T"],
+        row!["Skip silence".fg(Magenta), "Shift + S"],
         row!["Queue".fg(Blue), "1"],
         row!["Browser".fg(Blue), "2"],
         row!["Playlists".fg(Blue), "3"],
         row!["Settings".fg(Blue), "4"],
+        row!["Duplicates".fg(Blue), "5"],
+        row!["Stats".fg(Blue), "6"],
+        row!["Scan errors".fg(Blue), "7"],
         row!["Search".fg(Blue), "/"],
         row!["Exit Search".fg(Blue), "Escape | Tab"],
         row!["Select all".fg(Cyan), "Control + A"],
@@ -36,11 +41,13 @@ pub static HELP: LazyLock<[Row; 32]> = LazyLock::new(|| {
         row!["Move song margin".fg(Green), "F1 / Shift + F1"],
         row!["Move album margin".fg(Green), "F2 / Shift + F2"],
         row!["Move artist margin".fg(Green), "F3 / Shift + F3"],
+        row!["Edit tags".fg(Yellow), "R"],
         row!["Update database".fg(Yellow), "U"],
         row!["Quit player".fg(Yellow), "Ctrl + C"],
         row!["Clear queue".fg(Red), "C"],
         row!["Clear except playing".fg(Red), "Shift + C"],
         row!["Delete song/playlist".fg(Red), "X"],
         row!["Delete without confirmation".fg(Red), "Shift + X"],
+        row!["Ignore file with scan error".fg(Red), "X"],
     ]
 });
@@ -1,23 +1,36 @@
+use art::Cover;
 use browser::Browser;
-use gonk_core::{vdb::*, *};
+use duplicates::Duplicates;
+use editor::{Editor, Selection};
+use errors::Errors;
+use gonk_core::{vdb::*, watch::Watcher, *};
 use gonk_player::*;
 use mini::defer_results;
 use playlist::{Mode as PlaylistMode, Playlist};
 use queue::Queue;
 use search::{Mode as SearchMode, Search};
 use settings::Settings;
+use sleep::Sleep;
 use std::{
-    fs,
+    fs, mem,
+    path::{Path, PathBuf},
+    thread::JoinHandle,
     time::{Duration, Instant},
 };
 use winter::*;
 
+mod art;
 mod browser;
+mod duplicates;
+mod editor;
+mod errors;
 mod help;
 mod playlist;
 mod queue;
 mod search;
 mod settings;
+mod sleep;
+mod stats;
 
 const JUMP_AMOUNT: usize = 3;
 const FRAME_TIME: f32 = 1000.0 / 300.0;
@@ -35,6 +48,9 @@ pub enum Mode {
     Playlist,
     Settings,
     Search,
+    Duplicates,
+    Stats,
+    Errors,
 }
 
 fn draw(
@@ -42,6 +58,7 @@ fn draw(
     mode: &Mode,
     browser: &mut Browser,
     settings: &Settings,
+    persist: &gonk_core::settings::Settings,
     queue: &mut Queue,
     playlist: &mut Playlist,
     search: &mut Search,
@@ -51,6 +68,12 @@ fn draw(
     mouse: Option<(u16, u16)>,
     help: bool,
     mute: bool,
+    sleep: &Sleep,
+    cover: &mut Cover,
+    editor: Option<&Editor>,
+    duplicates: &Duplicates,
+    stats: Option<&gonk_core::stats::Stats>,
+    errors: &Errors,
 ) {
     let viewport = winter.viewport;
     let buf = winter.buffer();
@@ -70,12 +93,32 @@ fn draw(
         _ => *cursor = None,
     }
 
+    //Popups would be drawn under the cover.
+    cover.enabled = persist.album_art && !cover.failed && !help && editor.is_none();
+    let art = match mode {
+        _ if !cover.enabled => None,
+        Mode::Browser => {
+            browser::selected_album(browser).and_then(|(artist, album)| db.art(artist, album))
+        }
+        Mode::Queue => songs
+            .selected()
+            .and_then(|song| db.art(&song.artist, &song.album)),
+        _ => None,
+    };
+
     match mode {
-        Mode::Browser => browser::draw(browser, area, buf, mouse),
-        Mode::Settings => settings::draw(settings, area, buf),
-        Mode::Queue => queue::draw(queue, area, buf, mouse, songs, mute),
+        Mode::Browser => browser::draw(browser, area, buf, mouse, cover, art),
+        Mode::Settings => settings::draw(settings, persist, area, buf),
+        Mode::Queue => queue::draw(queue, area, buf, mouse, songs, mute, sleep, cover, art),
         Mode::Playlist => *cursor = playlist::draw(playlist, area, buf, mouse),
         Mode::Search => *cursor = search::draw(search, area, buf, mouse, db),
+        Mode::Duplicates => duplicates::draw(duplicates, area, buf),
+        Mode::Stats => stats::draw(stats, area, buf),
+        Mode::Errors => errors::draw(errors, area, buf),
+    }
+
+    if let Some(editor) = editor {
+        *cursor = editor::draw(editor, area, buf);
     }
 
     if help {
@@ -106,6 +149,11 @@ fn main() {
     let args: Vec<String> = std::env::args().skip(1).collect();
     let mut scan_timer = Instant::now();
     let mut scan_handle = None;
+    let mut update_handle: Option<JoinHandle<db::ScanResult>> = None;
+    let mut edit_handle: Option<JoinHandle<Vec<(PathBuf, Result<(), edit::TagError>)>>> = None;
+    //Files waiting to be updated in the database.
+    let mut pending: Vec<PathBuf> = Vec::new();
+    let mut watcher: Option<Watcher> = None;
 
     if !args.is_empty() {
         match args[0].as_str() {
@@ -116,25 +164,220 @@ fn main() {
 
                 match path(args[1].clone()) {
                     Some(path) if path.exists() => {
-                        persist.music_folder = path.to_string_lossy().to_string();
-                        scan_handle = Some(db::create(&persist.music_folder));
+                        let path = path.to_string_lossy().to_string();
+                        if !persist.music_folders.contains(&path) {
+                            persist.music_folders.push(path);
+                        }
+                        scan_handle = Some(db::create(
+                            &persist.music_folders,
+                            walk::Options::from(&persist),
+                        ));
                         scan_timer = Instant::now();
                     }
                     _ => return println!("Invalid path."),
                 }
             }
+            "remove" => {
+                if args.len() == 1 {
+                    return println!("Usage: gonk remove <path>");
+                }
+
+                //The folder might not exist anymore.
+                let folder = match path(args[1].clone()) {
+                    Some(path) => path.to_string_lossy().to_string(),
+                    None => args[1].clone(),
+                };
+                let Some(index) = persist.music_folders.iter().position(|f| *f == folder) else {
+                    return println!("{folder} is not in the library, see `gonk folders`.");
+                };
+                persist.music_folders.remove(index);
+                if let Err(e) = persist.save() {
+                    return println!("Failed to save settings! {e}");
+                }
+
+                println!("Removing songs from {folder}...");
+                return match db::create(&persist.music_folders, walk::Options::from(&persist))
+                    .join()
+                    .unwrap()
+                {
+                    db::ScanResult::Completed(changes)
+                    | db::ScanResult::CompletedWithErrors(changes, _) => {
+                        println!("Removed {folder}, {changes}.")
+                    }
+                    db::ScanResult::FileInUse => {
+                        println!("Could not update database, file in use.")
+                    }
+                };
+            }
+            "ignore" | "unignore" => {
+                let Some(pattern) = args.get(1) else {
+                    if persist.ignore.is_empty() {
+                        return println!("Usage: gonk ignore <pattern>");
+                    }
+                    for pattern in &persist.ignore {
+                        println!("{pattern}");
+                    }
+                    return;
+                };
+
+                if args[0] == "ignore" {
+                    if !persist.ignore.contains(pattern) {
+                        persist.ignore.push(pattern.clone());
+                    }
+                } else {
+                    let len = persist.ignore.len();
+                    persist.ignore.retain(|p| p != pattern);
+                    if persist.ignore.len() == len {
+                        return println!("{pattern} is not ignored, see `gonk ignore`.");
+                    }
+                }
+                if let Err(e) = persist.save() {
+                    return println!("Failed to save settings! {e}");
+                }
+
+                println!("Updating library...");
+                return match db::create(&persist.music_folders, walk::Options::from(&persist))
+                    .join()
+                    .unwrap()
+                {
+                    db::ScanResult::Completed(changes)
+                    | db::ScanResult::CompletedWithErrors(changes, _) => {
+                        println!("Updated library, {changes}.")
+                    }
+                    db::ScanResult::FileInUse => {
+                        println!("Could not update database, file in use.")
+                    }
+                };
+            }
+            "folders" => {
+                if persist.music_folders.is_empty() {
+                    return println!("No music folders, add one with `gonk add <path>`.");
+                }
+                for folder in &persist.music_folders {
+                    println!("{}", folder.replace("\\\\?\\", ""));
+                }
+                return;
+            }
+            "render" => {
+                let usage = "Usage: gonk render <path|playlist> -o <output.wav> [--bits 16|24|32]";
+                let Some(input) = args.get(1) else {
+                    return println!("{usage}");
+                };
+                let Some(output) = args
+                    .iter()
+                    .position(|arg| arg == "-o" || arg == "--output")
+                    .and_then(|i| args.get(i + 1))
+                else {
+                    return println!("{usage}");
+                };
+
+                let songs = match path(input.clone()) {
+                    Some(path) if path.is_file() => match Song::try_from(path.as_path()) {
+                        Ok(song) => vec![song],
+                        Err(err) => return println!("{err}"),
+                    },
+                    _ => match gonk_core::playlist::playlists()
+                        .into_iter()
+                        .find(|playlist| playlist.name() == input)
+                    {
+                        Some(playlist) => playlist.songs.to_vec(),
+                        None => return println!("Invalid path or playlist."),
+                    },
+                };
+
+                //32-bit float is used unless an integer bit depth is given.
+                let format = match args.iter().position(|arg| arg == "--bits") {
+                    Some(i) => match args
+                        .get(i + 1)
+                        .and_then(|bits| bits.parse::<u16>().ok())
+                        .and_then(format::SampleFormat::from_bits)
+                    {
+                        Some(format) => format,
+                        None => return println!("{usage}"),
+                    },
+                    None => format::SampleFormat::F32,
+                };
+
+                //Render with the same settings used for playback.
+                settings::apply(&persist);
+
+                let mut chain = dsp::Chain::default();
+                dsp::apply_edits(&mut chain);
+
+                let timer = Instant::now();
+                return match gonk_player::render(&songs, output, format, &mut chain) {
+                    Ok(duration) => println!(
+                        "Rendered {:.2} seconds of audio to {} in {:.2} seconds.",
+                        duration.as_secs_f32(),
+                        output,
+                        timer.elapsed().as_secs_f32()
+                    ),
+                    Err(e) => println!("Failed to render! {e}"),
+                };
+            }
+            "preamp" => {
+                let Some(db) = args.get(1).and_then(|db| db.parse::<f32>().ok()) else {
+                    return println!(
+                        "Current preamp is {} dB. Usage: gonk preamp <dB>",
+                        persist.preamp
+                    );
+                };
+                persist.preamp = db.clamp(-20.0, 20.0);
+                return match persist.save() {
+                    Ok(_) => println!("Preamp set to {} dB.", persist.preamp),
+                    Err(e) => println!("Failed to save settings! {e}"),
+                };
+            }
             "reset" => {
                 return match gonk_core::db::reset() {
                     Ok(_) => println!("Database reset!"),
                     Err(e) => println!("Failed to reset database! {e}"),
                 };
             }
+            "duplicates" => {
+                let songs = match db::read() {
+                    Ok(songs) => songs,
+                    Err(e) => return println!("Failed to read database! {e}"),
+                };
+                let groups = gonk_core::duplicates::find(&songs);
+                if groups.is_empty() {
+                    return println!("No duplicates found.");
+                }
+
+                for group in &groups {
+                    let song = &group[0];
+                    println!("{} - {} - {}", song.artist, song.album, song.title);
+                    for song in group {
+                        println!(
+                            "   {} ({}, {})",
+                            song.path,
+                            gonk_core::duplicates::describe(song),
+                            format_duration(song.duration)
+                        );
+                    }
+                }
+
+                let extra: usize = groups.iter().map(|group| group.len() - 1).sum();
+                let s = if groups.len() == 1 { "" } else { "s" };
+                let copies = if extra == 1 { "copy" } else { "copies" };
+                return println!("Found {} group{s}, {extra} extra {copies}.", groups.len());
+            }
             "help" | "--help" => {
                 println!("Usage");
                 println!("   gonk [<command> <args>]");
                 println!();
                 println!("Options");
                 println!("   add    <path> Add music to the library");
+                println!("   remove <path> Remove a folder from the library");
+                println!("   folders       List the folders in the library");
+                println!(
+                    "   ignore   [pattern] Exclude files from the library, e.g. **/Samples/**"
+                );
+                println!("   unignore <pattern> Remove an ignore pattern");
+                println!("   render <path|playlist> -o <output.wav> [--bits 16|24|32]");
+                println!("                 Render a song or playlist to a WAV file");
+                println!("   preamp <dB>   Set the preamp, the limiter prevents clipping");
+                println!("   duplicates    List songs that are likely duplicates");
                 println!("   reset         Reset the database");
                 println!("   buffer <size> Set a custom ring buffer size");
                 return;
@@ -178,9 +421,11 @@ fn main() {
     });
 
     let mut winter = Winter::new();
+    let mut cover = Cover::new();
     let index = (!persist.queue.is_empty()).then_some(persist.index as usize);
 
     set_volume(persist.volume);
+    settings::apply(&persist);
 
     let mut songs = Index::new(persist.queue.clone(), index);
     if let Some(song) = songs.selected() {
@@ -191,11 +436,15 @@ fn main() {
 
     let mut db = Database::new();
     let mut browser = Browser::new(&db);
+    pending.extend(db::unscanned());
 
     //Everything here initialises quickly.
     let mut queue = Queue::new(index.unwrap_or(0));
     let mut playlist = Playlist::new().unwrap();
     let mut search = Search::new();
+    let mut duplicates = Duplicates::new();
+    let mut stats = None;
+    let mut errors = Errors::new();
     let mut mode = Mode::Browser;
     let mut last_tick = Instant::now();
     let mut ft = Instant::now();
@@ -203,8 +452,11 @@ fn main() {
     let mut help = false;
     let mut prev_mode = Mode::Search; //Used for search.
     let mut mute = false;
+    let mut sleep = Sleep::Off;
+    let mut preloaded = String::new();
     let mut old_volume = 0;
     let mut cursor: Option<(u16, u16)> = None;
+    let mut editor: Option<Editor> = None;
     let mut shift;
     let mut control;
 
@@ -224,6 +476,9 @@ fn main() {
                 Mode::Playlist => playlist::up(&mut playlist, amount),
                 Mode::Settings => settings::up(&mut settings, amount),
                 Mode::Search => search.results.up_n(amount),
+                Mode::Duplicates => duplicates::up(&mut duplicates, amount),
+                Mode::Stats => {}
+                Mode::Errors => errors::up(&mut errors, amount),
             }
         }};
     }
@@ -237,6 +492,9 @@ fn main() {
                 Mode::Playlist => playlist::down(&mut playlist, amount),
                 Mode::Settings => settings::down(&mut settings, amount),
                 Mode::Search => search.results.down_n(amount),
+                Mode::Duplicates => duplicates::down(&mut duplicates, amount),
+                Mode::Stats => {}
+                Mode::Errors => errors::down(&mut errors, amount),
             }
         }};
     }
@@ -246,6 +504,7 @@ fn main() {
             match mode {
                 Mode::Browser => browser::left(&mut browser),
                 Mode::Playlist => playlist::left(&mut playlist),
+                Mode::Settings => settings::left(&settings, &mut persist),
                 _ => {}
             }
         };
@@ -256,6 +515,7 @@ fn main() {
             match mode {
                 Mode::Browser => browser::right(&mut browser),
                 Mode::Playlist => playlist::right(&mut playlist),
+                Mode::Settings => settings::right(&settings, &mut persist),
                 _ => {}
             }
         };
@@ -271,26 +531,21 @@ fn main() {
                 log::clear();
 
                 match result {
-                    db::ScanResult::Completed => {
+                    db::ScanResult::Completed(changes) => {
                         log!(
-                            "Finished adding {} files in {:.2} seconds.",
+                            "Finished scanning {} files in {:.2} seconds, {changes}.",
                             db.len,
                             scan_timer.elapsed().as_secs_f32()
                         );
                     }
-                    db::ScanResult::CompletedWithErrors(errors) => {
-                        let dir = "See %appdata%/gonk/gonk.log for details.";
+                    db::ScanResult::CompletedWithErrors(changes, errors) => {
                         let len = errors.len();
                         let s = if len == 1 { "" } else { "s" };
 
                         log!(
-                            "Added {} files with {len} error{s}. {dir}",
-                            db.len.saturating_sub(len)
+                            "Scanned {} files with {len} error{s}, {changes}. Press 7 for details.",
+                            db.len
                         );
-
-                        let path = gonk_path().join("gonk.log");
-                        let errors = errors.join("\n");
-                        fs::write(path, errors).unwrap();
                     }
                     db::ScanResult::FileInUse => {
                         log!("Could not update database, file in use.")
@@ -298,6 +553,13 @@ fn main() {
                 }
 
                 browser::refresh(&mut browser, &db);
+                if mode == Mode::Duplicates {
+                    duplicates::refresh(&mut duplicates, &db);
+                }
+                if mode == Mode::Stats {
+                    stats = stats::refresh();
+                }
+                errors::refresh(&mut errors);
                 search.results = Index::new(db.search(&search.query), None);
 
                 //No need to reset scan_timer since it's reset with new scans.
@@ -305,6 +567,84 @@ fn main() {
             }
         }
 
+        if update_handle
+            .as_ref()
+            .is_some_and(|handle| handle.is_finished())
+        {
+            let result = update_handle.take().unwrap().join().unwrap();
+            let changes = match result {
+                db::ScanResult::Completed(changes) => changes,
+                db::ScanResult::CompletedWithErrors(changes, errors) => {
+                    let len = errors.len();
+                    let s = if len == 1 { "" } else { "s" };
+                    log!("Failed to scan {len} file{s}, press 7 for details.");
+                    changes
+                }
+                db::ScanResult::FileInUse => {
+                    log!("Could not update database, file in use.");
+                    db::Changes::default()
+                }
+            };
+
+            if changes != db::Changes::default() {
+                log!("Library updated, {changes}.");
+                db = Database::new();
+                browser::refresh(&mut browser, &db);
+                if mode == Mode::Duplicates {
+                    duplicates::refresh(&mut duplicates, &db);
+                }
+                if mode == Mode::Stats {
+                    stats = stats::refresh();
+                }
+                search.results = Index::new(db.search(&search.query), None);
+            }
+            errors::refresh(&mut errors);
+        }
+
+        if edit_handle
+            .as_ref()
+            .is_some_and(|handle| handle.is_finished())
+        {
+            let results = edit_handle.take().unwrap().join().unwrap();
+            let len = results.len();
+            let mut errors = Vec::new();
+
+            for (path, result) in results {
+                match result {
+                    Ok(()) => {
+                        //Songs in the queue are copies, so they need updating too.
+                        for song in songs
+                            .iter_mut()
+                            .filter(|song| Path::new(&song.path) == path)
+                        {
+                            if let Ok(new) = Song::try_from(path.as_path()) {
+                                *song = new;
+                            }
+                        }
+                        pending.push(path);
+                    }
+                    Err(err) => errors.push(format!("{}: {err}", path.display())),
+                }
+            }
+
+            if errors.is_empty() {
+                let s = if len == 1 { "" } else { "s" };
+                log!("Updated tags in {len} file{s}.");
+            } else {
+                //Only one message fits, the rest go in a report.
+                let failed = errors.len();
+                let report = gonk_path().join("edit_errors.txt");
+                if failed > 1 && fs::write(&report, errors.join("\n")).is_ok() {
+                    log!(
+                        "Failed to update {failed} of {len} files, see {}.",
+                        report.display()
+                    );
+                } else {
+                    log!("Failed to update {failed} of {len} files, {}", errors[0]);
+                }
+            }
+        }
+
         if last_tick.elapsed() >= Duration::from_millis(150) {
             if scan_handle.is_some() {
                 if dots < 3 {
@@ -315,7 +655,7 @@ fn main() {
                 log!(
                     "Scanning {} for files{}",
                     //Remove the UNC \\?\ from the path.
-                    &persist.music_folder.replace("\\\\?\\", ""),
+                    &persist.music_folders.join(", ").replace("\\\\?\\", ""),
                     ".".repeat(dots)
                 );
             }
@@ -327,21 +667,92 @@ fn main() {
             persist.save().unwrap();
 
             //Update the list of output devices
-            settings.devices = devices();
-            let mut index = settings.index.unwrap_or(0);
-            if index >= settings.devices.len() {
-                index = settings.devices.len().saturating_sub(1);
-                settings.index = Some(index);
+            let device_list = devices();
+            for device in &device_list {
+                if !settings.devices.iter().any(|d| d.name == device.name) {
+                    log!("Connected {}.", device.name);
+                }
+            }
+            for device in &settings.devices {
+                if !device_list.iter().any(|d| d.name == device.name) {
+                    log!("Disconnected {}.", device.name);
+                }
+            }
+            settings.devices = device_list;
+            settings.index = settings.index.min(settings::len(&settings) - 1);
+
+            //Switch back to the preferred device when it's plugged back in.
+            let current = current_device();
+            if !current.is_empty()
+                && !persist.output_device.is_empty()
+                && current != persist.output_device
+                && settings
+                    .devices
+                    .iter()
+                    .any(|d| d.name == persist.output_device)
+            {
+                let _ = set_output_device(&persist.output_device);
+            }
+            settings.current_device = current;
+
+            //Start or stop watching the music folder.
+            if persist.watch && !persist.music_folders.is_empty() {
+                if watcher.as_ref().is_none_or(|watcher| {
+                    watcher.folders != persist.music_folders
+                        || watcher.options != walk::Options::from(&persist)
+                }) {
+                    watcher = Some(Watcher::new(
+                        &persist.music_folders,
+                        walk::Options::from(&persist),
+                    ));
+                }
+            } else {
+                watcher = None;
+            }
+
+            //Full scans already include any changes.
+            if scan_handle.is_some() {
+                if let Some(watcher) = &watcher {
+                    watcher.changes();
+                }
+            } else if update_handle.is_none() {
+                if let Some(paths) = watcher.as_ref().and_then(Watcher::changes) {
+                    pending.extend(paths);
+                }
+                if !pending.is_empty() {
+                    update_handle = Some(db::update(
+                        mem::take(&mut pending),
+                        &persist.music_folders,
+                        walk::Options::from(&persist),
+                    ));
+                }
+            }
+
+            //Start loading the next song before it's needed.
+            if persist.read_ahead && persist.preload {
+                if let Some(index) = songs.index() {
+                    let next = &songs[(index + 1) % songs.len()];
+                    if next.path != preloaded {
+                        preload(next);
+                        preloaded = next.path.clone();
+                    }
+                }
             }
 
             last_tick = Instant::now();
         }
 
+        sleep::tick(&mut sleep, &songs);
+
         //Play the next song if the current is finished.
         if gonk_player::play_next() && !songs.is_empty() {
+            let stop = sleep::on_track_end(&mut sleep, &songs);
             songs.down();
             if let Some(song) = songs.selected() {
                 play_song(song);
+                if stop {
+                    pause();
+                }
             }
         }
 
@@ -353,6 +764,7 @@ fn main() {
             &mode,
             &mut browser,
             &settings,
+            &persist,
             &mut queue,
             &mut playlist,
             &mut search,
@@ -362,6 +774,12 @@ fn main() {
             None,
             help,
             mute,
+            &sleep,
+            &mut cover,
+            editor.as_ref(),
+            &duplicates,
+            stats.as_ref(),
+            &errors,
         );
 
         'events: {
@@ -373,12 +791,43 @@ fn main() {
             control = state.control();
 
             match event {
+                Event::Escape if editor.is_some() => editor = None,
+                Event::Enter if editor.is_some() => {
+                    if edit_handle.is_some() {
+                        log!("Still writing tags.");
+                    } else {
+                        let edits = editor::changes(editor.as_ref().unwrap());
+                        if !edits.is_empty() {
+                            edit_handle = Some(edit::write_all(edits));
+                        }
+                        editor = None;
+                    }
+                }
+                Event::Up | Event::ScrollUp if editor.is_some() => {
+                    editor::up(editor.as_mut().unwrap())
+                }
+                Event::Down | Event::ScrollDown | Event::Tab if editor.is_some() => {
+                    editor::down(editor.as_mut().unwrap())
+                }
+                Event::Backspace if editor.is_some() => {
+                    editor::on_backspace(editor.as_mut().unwrap(), control)
+                }
+                //Handle ^W as control backspace.
+                Event::Char('w') if control && editor.is_some() => {
+                    editor::on_backspace(editor.as_mut().unwrap(), true)
+                }
+                Event::Char(c) if editor.is_some() && !control => {
+                    editor::on_char(editor.as_mut().unwrap(), c)
+                }
+                Event::Left | Event::Right | Event::LeftMouse(..) | Event::Function(_)
+                    if editor.is_some() => {}
                 Event::LeftMouse(x, y) if !help => {
                     draw(
                         &mut winter,
                         &mode,
                         &mut browser,
                         &settings,
+                        &persist,
                         &mut queue,
                         &mut playlist,
                         &mut search,
@@ -388,6 +837,12 @@ fn main() {
                         Some((x, y)),
                         help,
                         mute,
+                        &sleep,
+                        &mut cover,
+                        editor.as_ref(),
+                        &duplicates,
+                        stats.as_ref(),
+                        &errors,
                     );
                 }
                 Event::ScrollUp => up!(),
@@ -474,16 +929,41 @@ fn main() {
                     Mode::Playlist => {
                         playlist::delete(&mut playlist, false);
                     }
+                    Mode::Errors => {
+                        if let Some(pattern) = errors::ignore(&mut errors, &mut persist) {
+                            log!("Ignoring {pattern}.");
+                        }
+                    }
                     _ => (),
                 },
                 //Force delete -> Shift + X.
                 Event::Char('X') if mode == Mode::Playlist => playlist::delete(&mut playlist, true),
-                Event::Char('u') if mode == Mode::Browser || mode == Mode::Playlist => {
-                    if scan_handle.is_none() {
-                        if persist.music_folder.is_empty() {
+                Event::Char('r') if mode == Mode::Queue => {
+                    let song = queue.index().and_then(|i| songs.get(i)).cloned();
+                    editor = Editor::new(song.into_iter().collect(), Selection::Song);
+                }
+                Event::Char('r') if mode == Mode::Browser && db.len != 0 => {
+                    let selection = match browser.mode {
+                        browser::Mode::Artist => Selection::Artist,
+                        browser::Mode::Album => Selection::Album,
+                        browser::Mode::Song => Selection::Song,
+                    };
+                    editor = Editor::new(browser::get_selected(&browser, &db), selection);
+                }
+                Event::Char('u')
+                    if mode == Mode::Browser || mode == Mode::Playlist || mode == Mode::Errors =>
+                {
+                    //Both scans replace the database, the last one would undo the other.
+                    if update_handle.is_some() {
+                        log!("The library is being updated, try again in a moment.");
+                    } else if scan_handle.is_none() {
+                        if persist.music_folders.is_empty() {
                             gonk_core::log!("Nothing to scan! Add a folder with 'gonk add /path/'");
                         } else {
-                            scan_handle = Some(db::create(&persist.music_folder));
+                            scan_handle = Some(db::create(
+                                &persist.music_folders,
+                                walk::Options::from(&persist),
+                            ));
                             scan_timer = Instant::now();
                             playlist.lists = Index::from(gonk_core::playlist::playlists());
                         }
@@ -499,6 +979,16 @@ fn main() {
                         set_volume(0);
                     }
                 }
+                Event::Char('t') => sleep::cycle(&mut sleep),
+                Event::Char('S') => {
+                    persist.skip_silence = !persist.skip_silence;
+                    set_skip_silence(persist.skip_silence);
+                    if persist.skip_silence {
+                        log!("Skipping silence.");
+                    } else {
+                        log!("Playing silence.");
+                    }
+                }
                 Event::Char('q') => seek_backward(),
                 Event::Char('e') => seek_foward(),
                 Event::Char('a') => {
@@ -542,6 +1032,11 @@ fn main() {
                 Event::Enter if mode == Mode::Browser => {
                     songs.extend(browser::get_selected(&browser, &db));
                 }
+                Event::Enter if mode == Mode::Duplicates => {
+                    if let Some(song) = duplicates::selected(&duplicates) {
+                        songs.push(song.clone());
+                    }
+                }
                 Event::Enter if mode == Mode::Queue && shift => {
                     if let Some(range) = &queue.range {
                         let mut playlist_songs = Vec::new();
@@ -562,14 +1057,20 @@ fn main() {
                         play_song(&songs[i]);
                     }
                 }
-                Event::Enter if mode == Mode::Settings => {
-                    if let Some(device) = settings::selected(&settings) {
+                Event::Enter if mode == Mode::Settings => match settings::selected(&settings) {
+                    Some(settings::Item::Device(device)) => {
                         let device = device.to_string();
-                        set_output_device(&device);
-                        settings.current_device = device.clone();
-                        persist.output_device = device.clone();
+                        match set_output_device(&device) {
+                            Ok(_) => {
+                                settings.current_device = device.clone();
+                                persist.output_device = device.clone();
+                            }
+                            Err(e) => log!("{e}"),
+                        }
                     }
-                }
+                    Some(settings::Item::Setting(setting)) => setting.adjust(&mut persist, 1),
+                    None => {}
+                },
                 Event::Enter if mode == Mode::Playlist => {
                     playlist::on_enter(&mut playlist, &mut songs, shift);
                 }
@@ -593,6 +1094,18 @@ fn main() {
                 Event::Char('2') => mode = Mode::Browser,
                 Event::Char('3') => mode = Mode::Playlist,
                 Event::Char('4') => mode = Mode::Settings,
+                Event::Char('5') => {
+                    duplicates::refresh(&mut duplicates, &db);
+                    mode = Mode::Duplicates;
+                }
+                Event::Char('6') => {
+                    stats = stats::refresh();
+                    mode = Mode::Stats;
+                }
+                Event::Char('7') => {
+                    errors::refresh(&mut errors);
+                    mode = Mode::Errors;
+                }
                 Event::Function(1) => queue::constraint(&mut queue, 0, shift),
                 Event::Function(2) => queue::constraint(&mut queue, 1, shift),
                 Event::Function(3) => queue::constraint(&mut queue, 2, shift),
@@ -613,7 +1126,15 @@ fn main() {
             }
         }
 
+        //Images are written outside of the buffer.
+        let erased = art::erase(&mut cover, &mut winter.stdout, winter.viewport);
         winter.draw();
+        let presented = art::present(&mut cover, &mut winter.stdout);
+        if let Err(e) = erased.and(presented) {
+            //Only for this session, the error might not happen next time.
+            log!("Failed to draw album art, it has been turned off until gonk is restarted. {e}");
+            cover.failed = true;
+        }
 
         //Move cursor
         if let Some((x, y)) = cursor {
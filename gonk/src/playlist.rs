@@ -1,6 +1,6 @@
 use crate::{ALBUM, ARTIST, TITLE};
-use gonk_core::{Index, Song};
-use std::{error::Error, mem};
+use gonk_core::{format_duration, Index, Song};
+use std::{error::Error, mem, time::Duration};
 use winter::*;
 
 #[derive(PartialEq, Eq)]
@@ -224,7 +224,14 @@ pub fn draw(
         .symbol(symbol)
         .draw(horizontal[0], buf, playlist.lists.index());
 
-    let song_block = block().title("Songs").title_margin(1);
+    let title = match playlist.lists.selected() {
+        Some(selected) => {
+            let total: Duration = selected.songs.iter().map(|song| song.duration).sum();
+            format!("Songs ─ {}", format_duration(total))
+        }
+        None => String::from("Songs"),
+    };
+    let song_block = block().title(title.as_str()).title_margin(1);
     if let Some(selected) = playlist.lists.selected() {
         let rows: Vec<_> = selected
             .songs
@@ -1,8 +1,16 @@
-use crate::{ALBUM, ARTIST, NUMBER, SEEKER, TITLE};
+use crate::{
+    art::{self, Cover},
+    sleep::{self, Sleep},
+    ALBUM, ARTIST, NUMBER, SEEKER, TITLE,
+};
 use core::ops::Range;
-use gonk_core::{log, Index, Song};
+use gonk_core::{art::Art, format_duration, log, Index, Song};
+use std::{sync::Arc, time::Duration};
 use winter::*;
 
+//Narrower covers aren't worth the space taken from the table.
+const MIN_ART_WIDTH: u16 = 16;
+
 pub struct Queue {
     pub constraint: [u16; 4],
     //TODO: This doesn't remember the previous index after a selection.
@@ -80,6 +88,30 @@ pub fn down(queue: &mut Queue, songs: &Index<Song>, amount: usize) {
     }
 }
 
+//Unknown durations are left blank.
+fn length(song: &Song) -> String {
+    if song.duration.is_zero() {
+        String::new()
+    } else {
+        format_duration(song.duration)
+    }
+}
+
+///Time left until the end of the queue.
+fn remaining(songs: &Index<Song>) -> Duration {
+    let Some(index) = songs.index() else {
+        return Duration::ZERO;
+    };
+    let current = match songs.get(index) {
+        //The player knows the exact length once the song is decoding.
+        Some(_) if !gonk_player::duration().is_zero() => gonk_player::duration(),
+        Some(song) => song.duration,
+        None => Duration::ZERO,
+    };
+    let rest: Duration = songs.iter().skip(index + 1).map(|song| song.duration).sum();
+    current.saturating_sub(gonk_player::elapsed()) + rest
+}
+
 pub fn draw(
     queue: &mut Queue,
     viewport: winter::Rect,
@@ -87,6 +119,9 @@ pub fn draw(
     mouse: Option<(u16, u16)>,
     songs: &mut Index<Song>,
     mute: bool,
+    sleep: &Sleep,
+    cover: &mut Cover,
+    art: Option<Arc<Art>>,
 ) {
     let fill = viewport.height.saturating_sub(3 + 3);
     let area = layout(
@@ -101,15 +136,24 @@ pub fn draw(
     );
 
     //Header
+    let state = if songs.is_empty() {
+        "Stopped"
+    } else if gonk_player::is_paused() {
+        "Paused"
+    } else {
+        "Playing"
+    };
+    let mut title = state.to_string();
+    let remaining = remaining(songs);
+    if !remaining.is_zero() {
+        title.push_str(&format!(" ─ {} left", format_duration(remaining)));
+    }
+    if let Some(label) = sleep::label(sleep, songs) {
+        title.push_str(&format!(" ─ {label}"));
+    }
     block()
         .borders(Borders::TOP | Borders::LEFT | Borders::RIGHT)
-        .title(if songs.is_empty() {
-            "Stopped"
-        } else if gonk_player::is_paused() {
-            "Paused"
-        } else {
-            "Playing"
-        })
+        .title(title.as_str())
         .title_margin(1)
         .draw(area[0], buf);
 
@@ -163,14 +207,22 @@ pub fn draw(
         }
     }
 
-    let volume: Line<'_> = if mute {
-        "Mute─╮".into()
+    let volume = if mute {
+        text!("Mute─╮")
+    } else {
+        text!("Vol: {}%─╮", gonk_player::get_volume())
+    };
+    let volume: Line<'_> = if gonk_player::is_clipping() {
+        lines!("Clip ".fg(Red), volume)
+    } else if gonk_player::is_limiting() {
+        lines!("Limit ".fg(Yellow), volume)
     } else {
-        text!("Vol: {}%─╮", gonk_player::get_volume()).into()
+        volume.into()
     };
     volume.align(Right).draw(area[0], buf);
 
     let mut row_bounds = None;
+    let mut body = area[1];
 
     //Body
     if songs.is_empty() {
@@ -181,6 +233,33 @@ pub fn draw(
         };
         block.draw(area[1], buf);
     } else {
+        //The cover sits to the left of the table.
+        if let Some(art) = art.filter(|_| cover.enabled) {
+            let height = body.height.saturating_sub(1);
+            let width = (height * 2).min(body.width / 3);
+            if width >= MIN_ART_WIDTH {
+                let chunks = layout(
+                    body,
+                    Direction::Horizontal,
+                    &[
+                        Constraint::Length(width),
+                        Constraint::Length(body.width - width),
+                    ],
+                );
+                block()
+                    .borders(Borders::LEFT | Borders::BOTTOM)
+                    .draw(chunks[0], buf);
+                let inner = Rect {
+                    x: chunks[0].x + 1,
+                    y: chunks[0].y,
+                    width: width - 1,
+                    height,
+                };
+                art::draw(cover, &art, inner, buf);
+                body = chunks[1];
+            }
+        }
+
         let mut rows: Vec<Row> = songs
             .iter()
             .map(|song| {
@@ -189,7 +268,8 @@ pub fn draw(
                     song.track_number.to_string().fg(NUMBER),
                     song.title.as_str().fg(TITLE),
                     song.album.as_str().fg(ALBUM),
-                    song.artist.as_str().fg(ARTIST)
+                    song.artist.as_str().fg(ARTIST),
+                    length(song).fg(NUMBER)
                 ]
             })
             .collect();
@@ -215,7 +295,8 @@ pub fn draw(
                     song.track_number.to_string().fg(NUMBER),
                     song.title.as_str().fg(TITLE),
                     song.album.as_str().fg(ALBUM),
-                    song.artist.as_str().fg(ARTIST)
+                    song.artist.as_str().fg(ARTIST),
+                    length(song).fg(NUMBER)
                 ];
             }
 
@@ -231,7 +312,8 @@ pub fn draw(
                         song.track_number.to_string().bg(NUMBER).fg(Black).dim(),
                         song.title.as_str().bg(TITLE).fg(Black).dim(),
                         song.album.as_str().bg(ALBUM).fg(Black).dim(),
-                        song.artist.as_str().bg(ARTIST).fg(Black).dim()
+                        song.artist.as_str().bg(ARTIST).fg(Black).dim(),
+                        length(song).bg(NUMBER).fg(Black).dim()
                     ];
                 } else {
                     rows[index] = row![
@@ -239,7 +321,8 @@ pub fn draw(
                         song.track_number.to_string().fg(Black).bg(NUMBER).dim(),
                         song.title.as_str().fg(Black).bg(TITLE).dim(),
                         song.album.as_str().fg(Black).bg(ALBUM).dim(),
-                        song.artist.as_str().fg(Black).bg(ARTIST).dim()
+                        song.artist.as_str().fg(Black).bg(ARTIST).dim(),
+                        length(song).fg(Black).bg(NUMBER).dim()
                     ];
                 }
             }
@@ -251,6 +334,7 @@ pub fn draw(
             Constraint::Percentage(queue.constraint[1]),
             Constraint::Percentage(queue.constraint[2]),
             Constraint::Percentage(queue.constraint[3]),
+            Constraint::Length(7),
         ];
         let block = block().borders(Borders::LEFT | Borders::RIGHT | Borders::BOTTOM);
         let header = header![
@@ -258,11 +342,12 @@ pub fn draw(
             "#".bold(),
             "Title".bold(),
             "Album".bold(),
-            "Artist".bold()
+            "Artist".bold(),
+            "Time".bold()
         ];
         let table = table(rows, &con).header(header).block(block).spacing(1);
-        table.draw(area[1], buf, queue.index());
-        row_bounds = Some(table.get_row_bounds(queue.index(), table.get_row_height(area[1])));
+        table.draw(body, buf, queue.index());
+        row_bounds = Some(table.get_row_bounds(queue.index(), table.get_row_height(body)));
     };
 
     if log::last_message().is_none() {
@@ -326,8 +411,8 @@ pub fn draw(
 
         //Mouse support for the queue.
         if let Some((start, _)) = row_bounds {
-            //Check if you clicked on the header.
-            if y >= header_height {
+            //Check if you clicked on the header or the cover.
+            if y >= header_height && x >= body.x {
                 let index = (y - header_height) as usize + start;
 
                 //Make sure you didn't click on the seek bar
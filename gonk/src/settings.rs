@@ -1,64 +1,226 @@
+use gonk_core::settings::Settings as Persist;
 use gonk_player::*;
 use winter::*;
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Setting {
+    SkipSilence,
+    CacheSilence,
+    Preamp,
+    Limiter,
+    Dither,
+    Balance,
+    Mono,
+    SwapChannels,
+    Crossfeed,
+    ReadAhead,
+    Preload,
+    Watch,
+    FollowSymlinks,
+    SkipHidden,
+    AlbumArt,
+}
+
+pub const OPTIONS: [Setting; 15] = [
+    Setting::SkipSilence,
+    Setting::CacheSilence,
+    Setting::Preamp,
+    Setting::Limiter,
+    Setting::Dither,
+    Setting::Balance,
+    Setting::Mono,
+    Setting::SwapChannels,
+    Setting::Crossfeed,
+    Setting::ReadAhead,
+    Setting::Preload,
+    Setting::Watch,
+    Setting::FollowSymlinks,
+    Setting::SkipHidden,
+    Setting::AlbumArt,
+];
+
+impl Setting {
+    pub fn label(self) -> &'static str {
+        match self {
+            Setting::SkipSilence => "Skip silence",
+            Setting::CacheSilence => "Cache silence",
+            Setting::Preamp => "Preamp",
+            Setting::Limiter => "Limiter",
+            Setting::Dither => "Dither",
+            Setting::Balance => "Balance",
+            Setting::Mono => "Mono",
+            Setting::SwapChannels => "Swap channels",
+            Setting::Crossfeed => "Crossfeed",
+            Setting::ReadAhead => "Read-ahead",
+            Setting::Preload => "Preload next",
+            Setting::Watch => "Watch folder",
+            Setting::FollowSymlinks => "Follow symlinks",
+            Setting::SkipHidden => "Skip hidden",
+            Setting::AlbumArt => "Album art",
+        }
+    }
+
+    pub fn value(self, persist: &Persist) -> String {
+        let toggle = |enabled: bool| if enabled { "On" } else { "Off" }.to_string();
+        match self {
+            Setting::SkipSilence => toggle(persist.skip_silence),
+            Setting::CacheSilence => toggle(persist.cache_silence),
+            Setting::Preamp => format!("{:+.1} dB", persist.preamp),
+            Setting::Limiter => toggle(persist.limiter),
+            Setting::Dither => toggle(persist.dither),
+            Setting::Balance => match (persist.balance * 100.0).round() as i32 {
+                0 => "Center".to_string(),
+                b if b < 0 => format!("{}% Left", -b),
+                b => format!("{b}% Right"),
+            },
+            Setting::Mono => toggle(persist.mono),
+            Setting::SwapChannels => toggle(persist.swap_channels),
+            Setting::Crossfeed => toggle(persist.crossfeed),
+            Setting::ReadAhead => toggle(persist.read_ahead),
+            Setting::Preload => toggle(persist.preload),
+            Setting::Watch => toggle(persist.watch),
+            Setting::FollowSymlinks => toggle(persist.follow_symlinks),
+            Setting::SkipHidden => toggle(persist.skip_hidden),
+            Setting::AlbumArt => toggle(persist.album_art),
+        }
+    }
+
+    ///Toggle or step the setting, `direction` is -1 for left and 1 for right.
+    pub fn adjust(self, persist: &mut Persist, direction: i8) {
+        match self {
+            Setting::SkipSilence => persist.skip_silence = !persist.skip_silence,
+            Setting::CacheSilence => persist.cache_silence = !persist.cache_silence,
+            Setting::Preamp => {
+                persist.preamp = (persist.preamp + 0.5 * direction as f32).clamp(-20.0, 20.0)
+            }
+            Setting::Limiter => persist.limiter = !persist.limiter,
+            Setting::Dither => persist.dither = !persist.dither,
+            Setting::Balance => {
+                //Round to avoid drifting away from center.
+                let balance = persist.balance + 0.1 * direction as f32;
+                persist.balance = ((balance * 10.0).round() / 10.0).clamp(-1.0, 1.0);
+            }
+            Setting::Mono => persist.mono = !persist.mono,
+            Setting::SwapChannels => persist.swap_channels = !persist.swap_channels,
+            Setting::Crossfeed => persist.crossfeed = !persist.crossfeed,
+            Setting::ReadAhead => persist.read_ahead = !persist.read_ahead,
+            Setting::Preload => persist.preload = !persist.preload,
+            Setting::Watch => persist.watch = !persist.watch,
+            Setting::FollowSymlinks => persist.follow_symlinks = !persist.follow_symlinks,
+            Setting::SkipHidden => persist.skip_hidden = !persist.skip_hidden,
+            Setting::AlbumArt => persist.album_art = !persist.album_art,
+        }
+        apply(persist);
+    }
+}
+
+///Send the persisted settings to the player.
+pub fn apply(persist: &Persist) {
+    set_skip_silence(persist.skip_silence);
+    set_cache_silence(persist.cache_silence);
+    set_preamp(persist.preamp);
+    set_limiter(persist.limiter);
+    set_dither(persist.dither);
+    set_balance(persist.balance);
+    set_mono(persist.mono);
+    set_swap_channels(persist.swap_channels);
+    set_crossfeed(persist.crossfeed);
+    set_read_ahead(persist.read_ahead);
+}
+
+pub enum Item<'a> {
+    Device(&'a str),
+    Setting(Setting),
+}
+
 pub struct Settings {
     pub devices: Vec<Device>,
-    pub index: Option<usize>,
+    ///Output devices are listed first, followed by the options.
+    pub index: usize,
     pub current_device: String,
 }
 
 impl Settings {
     pub fn new(devices: Vec<Device>, current_device: String) -> Self {
         Self {
-            index: if devices.is_empty() { None } else { Some(0) },
+            index: 0,
             devices,
             current_device,
         }
     }
 }
 
-pub fn selected(settings: &Settings) -> Option<&str> {
-    if let Some(index) = settings.index {
-        if let Some(device) = settings.devices.get(index) {
-            return Some(&device.name);
-        }
+pub fn len(settings: &Settings) -> usize {
+    settings.devices.len() + OPTIONS.len()
+}
+
+pub fn selected(settings: &Settings) -> Option<Item> {
+    if let Some(device) = settings.devices.get(settings.index) {
+        return Some(Item::Device(&device.name));
     }
-    None
+    OPTIONS
+        .get(settings.index - settings.devices.len())
+        .map(|setting| Item::Setting(*setting))
 }
 
 pub fn up(settings: &mut Settings, amount: usize) {
-    if settings.devices.is_empty() {
-        return;
-    }
-    let Some(index) = settings.index else { return };
-    settings.index = Some(gonk_core::up(settings.devices.len(), index, amount));
+    settings.index = gonk_core::up(len(settings), settings.index, amount);
 }
 
 pub fn down(settings: &mut Settings, amount: usize) {
-    if settings.devices.is_empty() {
-        return;
+    settings.index = gonk_core::down(len(settings), settings.index, amount);
+}
+
+pub fn left(settings: &Settings, persist: &mut Persist) {
+    if let Some(Item::Setting(setting)) = selected(settings) {
+        setting.adjust(persist, -1);
+    }
+}
+
+pub fn right(settings: &Settings, persist: &mut Persist) {
+    if let Some(Item::Setting(setting)) = selected(settings) {
+        setting.adjust(persist, 1);
     }
-    let Some(index) = settings.index else { return };
-    settings.index = Some(gonk_core::down(settings.devices.len(), index, amount));
 }
 
 //TODO: I liked the old item menu bold selections instead of white background.
 //It doesn't work on most terminals though :(
-pub fn draw(settings: &Settings, area: winter::Rect, buf: &mut winter::Buffer) {
-    let mut items = Vec::new();
+pub fn draw(settings: &Settings, persist: &Persist, area: winter::Rect, buf: &mut winter::Buffer) {
+    let mut devices = Vec::new();
     for device in &settings.devices {
         let item = if device.name == settings.current_device {
             lines!(">> ".dim(), &device.name)
         } else {
             lines!("   ", &device.name)
         };
-        items.push(item);
+        devices.push(item);
+    }
+
+    let mut options = Vec::new();
+    for setting in OPTIONS {
+        options.push(lines!(
+            text!("   {:<16}", setting.label()),
+            text!("{}", setting.value(persist)).dim()
+        ));
     }
 
-    if let Some(index) = settings.index {
-        items[index].style = Some(fg(Black).bg(White));
+    let device_index = (settings.index < devices.len()).then_some(settings.index);
+    let option_index = settings.index.checked_sub(devices.len());
+
+    if let Some(index) = device_index {
+        devices[index].style = Some(fg(Black).bg(White));
+    }
+    if let Some(index) = option_index {
+        options[index].style = Some(fg(Black).bg(White));
     }
 
-    let list = list(&items).block(block().title("Output Device").title_margin(1));
-    list.draw(area, buf, settings.index);
+    let length = OPTIONS.len() as u16 + 2;
+    let fill = area.height.saturating_sub(length);
+    let area = layout(area, Vertical, &[Length(fill), Length(length)]);
+
+    let device_list = list(&devices).block(block().title("Output Device").title_margin(1));
+    device_list.draw(area[0], buf, device_index);
+
+    let option_list = list(&options).block(block().title("Options").title_margin(1));
+    option_list.draw(area[1], buf, option_index);
 }
@@ -0,0 +1,122 @@
+//! Sleep timer
+//!
+//! Fades out and pauses playback after a set time
+//! or at the end of the current track or album.
+use gonk_core::{log, Index, Song};
+use gonk_player::{duration, elapsed, pause, set_fade};
+use std::time::{Duration, Instant};
+
+const FADE: Duration = Duration::from_secs(10);
+const MINUTES: [u64; 4] = [15, 30, 45, 60];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sleep {
+    Off,
+    //Minutes, Deadline
+    Minutes(u64, Instant),
+    EndOfTrack,
+    EndOfAlbum,
+}
+
+///Move to the next timer option.
+pub fn cycle(sleep: &mut Sleep) {
+    let minutes = |m: u64| Sleep::Minutes(m, Instant::now() + Duration::from_secs(m * 60));
+
+    *sleep = match *sleep {
+        Sleep::Off => minutes(MINUTES[0]),
+        Sleep::Minutes(m, _) => match MINUTES.iter().position(|n| *n == m) {
+            Some(i) if i + 1 < MINUTES.len() => minutes(MINUTES[i + 1]),
+            _ => Sleep::EndOfTrack,
+        },
+        Sleep::EndOfTrack => Sleep::EndOfAlbum,
+        Sleep::EndOfAlbum => Sleep::Off,
+    };
+
+    //Undo any fade that was in progress.
+    set_fade(1.0);
+
+    match sleep {
+        Sleep::Off => log!("Sleep timer off."),
+        Sleep::Minutes(m, _) => log!("Stopping playback in {m} minutes."),
+        Sleep::EndOfTrack => log!("Stopping playback at the end of this track."),
+        Sleep::EndOfAlbum => log!("Stopping playback at the end of this album."),
+    }
+}
+
+//Is the playing song the last one of it's album in the queue.
+fn last_in_album(songs: &Index<Song>) -> bool {
+    let (Some(index), Some(song)) = (songs.index(), songs.selected()) else {
+        return true;
+    };
+    match songs.get(index + 1) {
+        Some(next) => next.album != song.album || next.artist != song.artist,
+        None => true,
+    }
+}
+
+///Time left before playback is stopped, if it's known.
+pub fn remaining(sleep: &Sleep, songs: &Index<Song>) -> Option<Duration> {
+    match sleep {
+        Sleep::Off => None,
+        Sleep::Minutes(_, deadline) => Some(deadline.saturating_duration_since(Instant::now())),
+        Sleep::EndOfAlbum if !last_in_album(songs) => None,
+        Sleep::EndOfTrack | Sleep::EndOfAlbum => Some(duration().saturating_sub(elapsed())),
+    }
+}
+
+///Text shown in the queue header.
+pub fn label(sleep: &Sleep, songs: &Index<Song>) -> Option<String> {
+    let remaining = remaining(sleep, songs).map(|remaining| {
+        let secs = remaining.as_secs();
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    });
+
+    match (sleep, remaining) {
+        (Sleep::Off, _) => None,
+        (Sleep::Minutes(_, _), Some(remaining)) => Some(format!("Sleep {remaining}")),
+        (Sleep::EndOfTrack, Some(remaining)) => Some(format!("Sleep {remaining} (track)")),
+        (Sleep::EndOfAlbum, Some(remaining)) => Some(format!("Sleep {remaining} (album)")),
+        (_, None) => Some(String::from("Sleep (album)")),
+    }
+}
+
+///Fade out the volume and pause once the timer runs out.
+///This should be called every frame.
+pub fn tick(sleep: &mut Sleep, songs: &Index<Song>) {
+    //Track timers are stopped by `on_track_end`.
+    let Some(remaining) = remaining(sleep, songs) else {
+        return;
+    };
+
+    if remaining.is_zero() {
+        if let Sleep::Minutes(_, _) = sleep {
+            stop(sleep);
+        }
+    } else if remaining < FADE {
+        set_fade(remaining.as_secs_f32() / FADE.as_secs_f32());
+    } else {
+        set_fade(1.0);
+    }
+}
+
+///Returns true if playback should stop instead of moving to the next song.
+pub fn on_track_end(sleep: &mut Sleep, songs: &Index<Song>) -> bool {
+    let stop_playback = match sleep {
+        Sleep::EndOfTrack => true,
+        Sleep::EndOfAlbum => last_in_album(songs),
+        Sleep::Off | Sleep::Minutes(_, _) => false,
+    };
+
+    if stop_playback {
+        stop(sleep);
+    }
+
+    stop_playback
+}
+
+fn stop(sleep: &mut Sleep) {
+    pause();
+    set_fade(1.0);
+    *sleep = Sleep::Off;
+    log!("Sleep timer finished.");
+}
@@ -0,0 +1,111 @@
+use crate::{ARTIST, NUMBER};
+use gonk_core::{
+    db, format_duration, log,
+    stats::{collect, format_size, Stats},
+};
+use winter::*;
+
+///Reads the database again since the sizes aren't kept in memory.
+pub fn refresh() -> Option<Stats> {
+    match db::read_entries() {
+        Ok(entries) => Some(collect(&entries)),
+        Err(e) => {
+            log!("Failed to read database! {e}");
+            None
+        }
+    }
+}
+
+fn item<'a>(label: &'a str, value: String) -> Line<'a> {
+    lines!(text!("   {:<20}", label), value.fg(NUMBER))
+}
+
+fn percent(count: usize, total: usize) -> String {
+    format!(
+        "{count} ({:.0}%)",
+        count as f32 / total.max(1) as f32 * 100.0
+    )
+}
+
+pub fn draw(stats: Option<&Stats>, area: Rect, buf: &mut Buffer) {
+    let Some(stats) = stats else {
+        return lines!("Could not read the database.".dim())
+            .block(block().title("Stats").title_margin(1))
+            .draw(area, buf);
+    };
+
+    let library = [
+        item("Artists", stats.artists.to_string()),
+        item("Albums", stats.albums.to_string()),
+        item("Songs", stats.songs.to_string()),
+        item("Time", format_duration(stats.duration)),
+        item("Size", format_size(stats.size)),
+        item("Missing tags", percent(stats.missing_tags, stats.songs)),
+        item(
+            "Missing ReplayGain",
+            percent(stats.missing_gain, stats.songs),
+        ),
+    ];
+
+    let formats: Vec<Line> = stats
+        .formats
+        .iter()
+        .map(|(format, count)| item(format, percent(*count, stats.songs)))
+        .collect();
+
+    let rates: Vec<String> = stats
+        .sample_rates
+        .iter()
+        .map(|(rate, _)| match rate {
+            0 => "Unknown".to_string(),
+            rate => format!("{} kHz", *rate as f32 / 1000.0),
+        })
+        .collect();
+    let sample_rates: Vec<Line> = stats
+        .sample_rates
+        .iter()
+        .zip(&rates)
+        .map(|((_, count), rate)| item(rate, percent(*count, stats.songs)))
+        .collect();
+
+    let top_artists: Vec<Line> = stats
+        .top_artists
+        .iter()
+        .enumerate()
+        .map(|(i, (artist, count))| {
+            lines!(
+                text!("{:>4}. ", i + 1).dim(),
+                artist.as_str().fg(ARTIST),
+                text!(" {count}").fg(NUMBER)
+            )
+        })
+        .collect();
+
+    let columns = layout(
+        area,
+        Direction::Horizontal,
+        &[Constraint::Percentage(50), Constraint::Percentage(50)],
+    );
+    let left = layout(
+        columns[0],
+        Vertical,
+        &[
+            Length(library.len() as u16 + 2),
+            Length(formats.len() as u16 + 2),
+            Fill,
+        ],
+    );
+
+    list(&library)
+        .block(block().title("Library").title_margin(1))
+        .draw(left[0], buf, None);
+    list(&formats)
+        .block(block().title("Formats").title_margin(1))
+        .draw(left[1], buf, None);
+    list(&sample_rates)
+        .block(block().title("Sample rates").title_margin(1))
+        .draw(left[2], buf, None);
+    list(&top_artists)
+        .block(block().title("Top artists").title_margin(1))
+        .draw(columns[1], buf, None);
+}
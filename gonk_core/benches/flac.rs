@@ -6,7 +6,7 @@ fn custom(files: &[DirEntry]) -> Vec<Result<Song, String>> {
     files
         .iter()
         .map(|file| match read_metadata(&file.path) {
-            Ok(song) => Ok(song),
+            Ok((song, _)) => Ok(song),
             Err(err) => Err(format!("Error: ({err}) @ {}", file.path)),
         })
         .collect()
@@ -63,6 +63,7 @@ fn custom_old(files: &[DirEntry]) -> Vec<Result<Song, String>> {
                     track_number,
                     path: file.path.clone(),
                     gain,
+                    ..Song::default()
                 })
             }
             Err(err) => Err(format!("Error: ({err}) @ {}", file.path)),
@@ -167,6 +168,7 @@ fn symphonia(files: &[DirEntry]) -> Vec<Result<Song, String>> {
                 track_number,
                 path: entry.path.clone(),
                 gain,
+                ..Song::default()
             })
         })
         .collect()
@@ -0,0 +1,68 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use gonk_core::{db, id3, ogg, Song};
+use std::path::Path;
+use winwalk::DirEntry;
+
+fn native(files: &[DirEntry]) -> Vec<Result<Song, String>> {
+    files
+        .iter()
+        .map(|file| {
+            let path = Path::new(&file.path);
+            let song = match file.extension().and_then(|ex| ex.to_str()) {
+                Some("mp3") => id3::read_song(path)
+                    .map(|(song, _)| song)
+                    .map_err(|err| err.to_string()),
+                _ => ogg::read_song(path)
+                    .map(|(song, _)| song)
+                    .map_err(|err| err.to_string()),
+            };
+            song.map_err(|err| format!("Error: ({err}) @ {}", file.path))
+        })
+        .collect()
+}
+
+fn symphonia(files: &[DirEntry]) -> Vec<Result<Song, String>> {
+    files
+        .iter()
+        .map(|file| db::probe(Path::new(&file.path)).map_err(|err| err.to_string()))
+        .collect()
+}
+
+const PATH: &str = "D:\\OneDrive\\Music";
+
+fn files(extension: &str) -> Vec<DirEntry> {
+    winwalk::walkdir(PATH, 0)
+        .into_iter()
+        .flatten()
+        .filter(|entry| match entry.extension() {
+            Some(ex) => ex.to_str() == Some(extension),
+            None => false,
+        })
+        .collect()
+}
+
+fn tags(c: &mut Criterion) {
+    for extension in ["mp3", "ogg"] {
+        let mut group = c.benchmark_group(extension);
+        group.sample_size(10);
+
+        let paths = files(extension);
+
+        group.bench_function("native", |b| {
+            b.iter(|| {
+                native(black_box(&paths));
+            });
+        });
+
+        group.bench_function("symphonia", |b| {
+            b.iter(|| {
+                symphonia(black_box(&paths));
+            });
+        });
+
+        group.finish();
+    }
+}
+
+criterion_group!(benches, tags);
+criterion_main!(benches);
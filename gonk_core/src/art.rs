@@ -0,0 +1,233 @@
+//! Album art
+//!
+//! Covers are taken from the pictures embedded in a song, or an image like `cover.jpg`
+//! in the album folder. They're scaled down and cached as PNG thumbnails in `gonk_path()/art`.
+//! Thumbnails are named after the song and made again when it or its folder changes.
+//! Songs without art get an empty file so they aren't searched every time.
+use crate::{gonk_path, id3, ogg, read_flac, Picture, Song};
+use image::{DynamicImage, ImageFormat};
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, Cursor},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+///Thumbnails are scaled to fit in a square this size.
+pub const SIZE: u32 = 256;
+
+//See the ID3v2 APIC frame.
+const FRONT_COVER: u32 = 3;
+
+//Earlier names are preferred.
+const NAMES: [&str; 4] = ["cover", "folder", "front", "album"];
+const EXTENSIONS: [&str; 3] = ["jpg", "jpeg", "png"];
+
+pub struct Art {
+    pub width: u32,
+    pub height: u32,
+    ///8-bit RGB pixels, row by row.
+    pub rgb: Vec<u8>,
+    ///The cached thumbnail, for terminals that can display PNG directly.
+    pub png: Vec<u8>,
+}
+
+pub fn art_path() -> PathBuf {
+    gonk_path().join("art")
+}
+
+//FNV-1a, file names need to be the same between runs.
+fn hash(s: &str) -> u64 {
+    s.bytes().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn modified(path: &Path) -> u128 {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |time| time.as_nanos())
+}
+
+fn cache_path(source: &Path) -> PathBuf {
+    let key = source.display().to_string();
+    art_path().join(format!("{:016x}.png", hash(&key)))
+}
+
+//The thumbnail is out of date if the song or its folder changed after it was made.
+//Adding or replacing a cover image changes the folder's modified time.
+fn is_fresh(thumbnail: &Path, source: &Path) -> bool {
+    let changed = modified(source).max(source.parent().map_or(0, modified));
+    thumbnail.exists() && modified(thumbnail) >= changed
+}
+
+//Use the front cover if there is one.
+fn front(mut pictures: Vec<Picture>) -> Option<Vec<u8>> {
+    let i = pictures
+        .iter()
+        .position(|picture| picture.picture_type == FRONT_COVER)
+        .unwrap_or(0);
+    (i < pictures.len()).then(|| pictures.swap_remove(i).data)
+}
+
+///The encoded image embedded in a song.
+pub fn embedded(path: &Path) -> Option<Vec<u8>> {
+    let mut reader = BufReader::new(File::open(path).ok()?);
+    let pictures = match path.extension()?.to_str()? {
+        "flac" => read_flac(&mut reader, true).ok()?.pictures,
+        "mp3" => id3::read_tag(&mut reader).ok()??.pictures(),
+        "ogg" => ogg::pictures(&ogg::read_vorbis(&mut reader).ok()?.comments),
+        _ => return None,
+    };
+    front(pictures)
+}
+
+///An image like `cover.jpg` or `folder.png` in the same folder as the song.
+pub fn folder(path: &Path) -> Option<Vec<u8>> {
+    let mut images: Vec<(usize, PathBuf)> = fs::read_dir(path.parent()?)
+        .ok()?
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let stem = path.file_stem()?.to_str()?.to_ascii_lowercase();
+            let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+            let rank = NAMES.iter().position(|name| *name == stem)?;
+            EXTENSIONS
+                .contains(&extension.as_str())
+                .then_some((rank, path))
+        })
+        .collect();
+    images.sort();
+    fs::read(&images.first()?.1).ok()
+}
+
+//Decode and shrink the cover.
+fn thumbnail(source: &Path) -> Option<Vec<u8>> {
+    let bytes = embedded(source).or_else(|| folder(source))?;
+    let image = image::load_from_memory(&bytes).ok()?.thumbnail(SIZE, SIZE);
+    let image = DynamicImage::ImageRgb8(image.to_rgb8());
+
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .ok()?;
+    Some(png)
+}
+
+//Make the thumbnail and save it, replacing the old one.
+fn create_thumbnail(source: &Path, path: &Path) -> Option<Vec<u8>> {
+    let png = thumbnail(source);
+
+    //A missing song might come back later, so it isn't marked as having no art.
+    if png.is_some() || source.exists() {
+        let bytes = png.as_deref().unwrap_or_default();
+        //The art can still be shown if it can't be cached.
+        if let Err(err) = fs::create_dir_all(art_path()).and_then(|_| fs::write(path, bytes)) {
+            crate::log!("Failed to cache album art: {err}");
+        }
+    }
+
+    png
+}
+
+///Load the album art for a song, `None` if it doesn't have any.
+///
+///This reads and decodes images, so keep it off the UI thread.
+pub fn load(song: &Song) -> Option<Art> {
+    let source = Path::new(&song.path);
+    let path = cache_path(source);
+    let png = match fs::read(&path) {
+        Ok(png) if is_fresh(&path, source) => png,
+        _ => create_thumbnail(source, &path)?,
+    };
+    //An empty thumbnail means there's no art.
+    if png.is_empty() {
+        return None;
+    }
+
+    let rgb = image::load_from_memory_with_format(&png, ImageFormat::Png)
+        .ok()?
+        .to_rgb8();
+    Some(Art {
+        width: rgb.width(),
+        height: rgb.height(),
+        rgb: rgb.into_raw(),
+        png,
+    })
+}
+
+///Remove every cached thumbnail.
+pub fn clear() -> io::Result<()> {
+    match fs::remove_dir_all(art_path()) {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn folder_image() {
+        let dir = std::env::temp_dir().join("gonk_art");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("Folder.PNG"), b"folder").unwrap();
+        fs::write(dir.join("cover.jpg"), b"cover").unwrap();
+        fs::write(dir.join("back.jpg"), b"back").unwrap();
+
+        let song = dir.join("01.flac");
+        assert_eq!(folder(&song).unwrap(), b"cover");
+        fs::remove_file(dir.join("cover.jpg")).unwrap();
+        assert_eq!(folder(&song).unwrap(), b"folder");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn front_cover() {
+        let picture = |picture_type, data: &[u8]| Picture {
+            picture_type,
+            data: data.to_vec(),
+            ..Default::default()
+        };
+        assert_eq!(
+            front(vec![picture(4, b"back"), picture(3, b"front")]).unwrap(),
+            b"front"
+        );
+        assert_eq!(front(vec![picture(0, b"other")]).unwrap(), b"other");
+        assert!(front(Vec::new()).is_none());
+        assert_ne!(hash("a\tb"), hash("a\tc"));
+    }
+
+    #[test]
+    fn cache_key() {
+        let dir = std::env::temp_dir().join("gonk_art_key");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let song = dir.join("01.flac");
+        fs::write(&song, b"song").unwrap();
+
+        let thumbnail = cache_path(&song);
+        assert_ne!(cache_path(&dir.join("02.flac")), thumbnail);
+
+        //Thumbnails keep the same name so old ones are replaced.
+        let thumbnail = std::env::temp_dir().join("gonk_art_key.png");
+        let _ = fs::remove_file(&thumbnail);
+        assert!(!is_fresh(&thumbnail, &song));
+        fs::write(&thumbnail, b"").unwrap();
+        assert!(is_fresh(&thumbnail, &song));
+
+        let file = File::options().write(true).open(&song).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(60))
+            .unwrap();
+        assert!(!is_fresh(&thumbnail, &song));
+
+        fs::remove_file(thumbnail).unwrap();
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
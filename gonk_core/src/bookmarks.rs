@@ -0,0 +1,96 @@
+//! Resume positions for long files
+//!
+//! Audiobooks, podcasts and DJ mixes remember where they were left off.
+//! Each line stores a position in seconds and a path.
+//! The path goes last so it can contain tabs.
+use crate::{gonk_path, Deserialize, Serialize};
+use std::{collections::HashMap, fs, path::PathBuf, time::Duration};
+
+///Files shorter than this always start from the beginning.
+pub const THRESHOLD: Duration = Duration::from_secs(20 * 60);
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Bookmarks {
+    path: PathBuf,
+    positions: HashMap<String, f32>,
+}
+
+impl Bookmarks {
+    pub fn new() -> Self {
+        let path = gonk_path().join("bookmarks.db");
+        let mut bookmarks = match fs::read_to_string(&path) {
+            Ok(string) => Bookmarks::deserialize(&string).unwrap_or_default(),
+            Err(_) => Bookmarks::default(),
+        };
+        bookmarks.path = path;
+        bookmarks
+    }
+    pub fn get(&self, path: &str) -> Option<f32> {
+        self.positions.get(path).copied()
+    }
+    pub fn set(&mut self, path: &str, position: f32) {
+        self.positions.insert(path.to_string(), position);
+    }
+    pub fn remove(&mut self, path: &str) {
+        self.positions.remove(path);
+    }
+    pub fn save(&self) -> std::io::Result<()> {
+        fs::write(&self.path, self.serialize())
+    }
+}
+
+impl Serialize for Bookmarks {
+    fn serialize(&self) -> String {
+        let mut buffer = String::new();
+        for (path, position) in &self.positions {
+            //A newline would split the path, so it just won't be resumed.
+            if path.contains('\n') {
+                continue;
+            }
+            buffer.push_str(&position.to_string());
+            buffer.push('\t');
+            buffer.push_str(path);
+            buffer.push('\n');
+        }
+        buffer
+    }
+}
+
+impl Deserialize for Bookmarks {
+    type Error = Box<dyn std::error::Error>;
+
+    ///Lines that can't be read are skipped.
+    fn deserialize(s: &str) -> Result<Self, Self::Error> {
+        let mut positions = HashMap::new();
+        for line in s.lines() {
+            let Some((position, path)) = line.split_once('\t') else {
+                continue;
+            };
+            if let Ok(position) = position.parse::<f32>() {
+                positions.insert(path.to_string(), position);
+            }
+        }
+        Ok(Self {
+            path: PathBuf::new(),
+            positions,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn string() {
+        let mut bookmarks = Bookmarks::default();
+        bookmarks.set("D:\\Podcasts\\episode 1.mp3", 1234.5);
+        bookmarks.set("D:\\Mixes\\mix.flac", 60.0);
+        bookmarks.set("D:\\Mixes\\tab\tmix.flac", 30.0);
+        let string = bookmarks.serialize();
+        assert_eq!(Bookmarks::deserialize(&string).unwrap(), bookmarks);
+
+        let string = format!("{string}broken\nNaN?\tD:\\episode 2.mp3\n");
+        assert_eq!(Bookmarks::deserialize(&string).unwrap(), bookmarks);
+    }
+}
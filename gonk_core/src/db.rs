@@ -1,20 +1,61 @@
+//! Physical database
+//!
+//! Songs are stored in a compact binary format:
+//!
+//! ```text
+//! magic "GONK", version u16
+//! string count u32, strings (length u32, utf-8 bytes)
+//! song count u32, songs
+//! ```
+//!
+//! Each song stores indices into the string table for the title, album, artist,
+//! directory and file name. Repeated strings like album names and folders are only stored once.
+//! The disc and track numbers are `u16`. The file size and modification time come next
+//! so rescans can skip unchanged files, followed by the track artist, genre, composer, codec,
+//! year, duration (milliseconds), sample rate, bit depth, channel count and bitrate (kbps).
+//! All numbers are little endian.
+//!
+//! Version 0 is the original tab-separated text format which has no header.
+//! It's migrated to the binary format when it's read.
 use crate::*;
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
 use std::{
+    collections::{HashMap, HashSet},
     fs::File,
-    io::{BufWriter, Write},
+    io::{self, BufWriter, Write},
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
     thread::{self, JoinHandle},
+    time::{Duration, UNIX_EPOCH},
 };
 
+const MAGIC: &[u8; 4] = b"GONK";
+pub const VERSION: u16 = 1;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Song {
     pub title: String,
     pub album: String,
+    ///The album artist, or the track artist if there isn't one.
     pub artist: String,
-    pub disc_number: u8,
-    pub track_number: u8,
+    pub disc_number: u16,
+    pub track_number: u16,
     pub path: String,
     pub gain: f32,
+    pub track_artist: String,
+    ///Multiple genres are separated by `; `.
+    pub genre: String,
+    pub composer: String,
+    ///Zero if unknown.
+    pub year: u16,
+    pub duration: Duration,
+    pub codec: String,
+    pub sample_rate: u32,
+    ///Zero for lossy formats.
+    pub bit_depth: u8,
+    pub channels: u8,
+    ///Average bitrate in kbps.
+    pub bitrate: u32,
 }
 
 impl Serialize for Song {
@@ -30,7 +71,7 @@ impl Serialize for Song {
 
         let result = writeln!(
             &mut buffer,
-            "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
             escape(&self.title),
             escape(&self.album),
             escape(&self.artist),
@@ -38,6 +79,16 @@ impl Serialize for Song {
             self.track_number,
             escape(&self.path),
             gain,
+            escape(&self.track_artist),
+            escape(&self.genre),
+            escape(&self.composer),
+            self.year,
+            self.duration.as_millis(),
+            escape(&self.codec),
+            self.sample_rate,
+            self.bit_depth,
+            self.channels,
+            self.bitrate,
         );
 
         match result {
@@ -64,18 +115,37 @@ impl Deserialize for Song {
         };
 
         let mut parts = s.split('\t');
-        Ok(Song {
+        let mut song = Song {
             title: parts.next().ok_or("Missing title")?.to_string(),
             album: parts.next().ok_or("Missing album")?.to_string(),
             artist: parts.next().ok_or("Missing artist")?.to_string(),
-            disc_number: parts.next().ok_or("Missing disc_number")?.parse::<u8>()?,
-            track_number: parts.next().ok_or("Missing track_number")?.parse::<u8>()?,
+            disc_number: parts.next().ok_or("Missing disc_number")?.parse::<u16>()?,
+            track_number: parts.next().ok_or("Missing track_number")?.parse::<u16>()?,
             path: parts.next().ok_or("Missing path")?.to_string(),
             gain: parts.next().ok_or("Missing gain")?.parse::<f32>()?,
-        })
+            //Older playlists and queues don't have the other fields.
+            track_artist: parts.next().unwrap_or_default().to_string(),
+            genre: parts.next().unwrap_or_default().to_string(),
+            composer: parts.next().unwrap_or_default().to_string(),
+            year: parse_or(parts.next(), 0),
+            duration: Duration::from_millis(parse_or(parts.next(), 0)),
+            codec: parts.next().unwrap_or_default().to_string(),
+            sample_rate: parse_or(parts.next(), 0),
+            bit_depth: parse_or(parts.next(), 0),
+            channels: parse_or(parts.next(), 0),
+            bitrate: parse_or(parts.next(), 0),
+        };
+        if song.track_artist.is_empty() {
+            song.track_artist = song.artist.clone();
+        }
+        Ok(song)
     }
 }
 
+fn parse_or<T: FromStr>(s: Option<&str>, default: T) -> T {
+    s.and_then(|s| s.parse().ok()).unwrap_or(default)
+}
+
 impl Serialize for Vec<Song> {
     fn serialize(&self) -> String {
         let mut buffer = String::new();
@@ -108,6 +178,16 @@ impl Song {
             track_number: 1,
             path: String::new(),
             gain: 0.0,
+            track_artist: UNKNOWN_ARTIST.to_string(),
+            genre: String::new(),
+            composer: String::new(),
+            year: 0,
+            duration: Duration::ZERO,
+            codec: String::new(),
+            sample_rate: 0,
+            bit_depth: 0,
+            channels: 0,
+            bitrate: 0,
         }
     }
     pub fn example() -> Self {
@@ -119,10 +199,48 @@ impl Song {
             track_number: 1,
             path: "path".to_string(),
             gain: 1.0,
+            track_artist: "track artist".to_string(),
+            genre: "genre".to_string(),
+            composer: "composer".to_string(),
+            year: 2000,
+            duration: Duration::from_millis(180_500),
+            codec: "FLAC".to_string(),
+            sample_rate: 44100,
+            bit_depth: 16,
+            channels: 2,
+            bitrate: 900,
         }
     }
 }
 
+///The year from a date tag like `2001`, `2001-05-12` or `12/05/2001`.
+pub fn parse_year(date: &str) -> Option<u16> {
+    let bytes = date.as_bytes();
+    (0..bytes.len().saturating_sub(3))
+        .find(|&i| bytes[i..i + 4].iter().all(u8::is_ascii_digit))
+        .and_then(|i| date[i..i + 4].parse().ok())
+}
+
+///Add a genre to a list separated by `; `.
+pub fn push_genre(genre: &mut String, value: &str) {
+    let value = value.trim();
+    if value.is_empty() || genre.split("; ").any(|g| g == value) {
+        return;
+    }
+    if !genre.is_empty() {
+        genre.push_str("; ");
+    }
+    genre.push_str(value);
+}
+
+///Average bitrate in kbps.
+pub fn bitrate(size: u64, duration: Duration) -> u32 {
+    if duration.is_zero() {
+        return 0;
+    }
+    (size as f64 * 8.0 / duration.as_secs_f64() / 1000.0).round() as u32
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct Album {
     pub title: String,
@@ -135,188 +253,815 @@ pub struct Artist {
 }
 
 impl TryFrom<&Path> for Song {
-    type Error = String;
+    type Error = ScanError;
 
     fn try_from(path: &Path) -> Result<Self, Self::Error> {
-        let extension = path.extension().ok_or("Path is not audio")?;
+        read_song(path).map(|(song, _)| song)
+    }
+}
 
-        if extension != "flac" {
-            use symphonia::{
-                core::{formats::FormatOptions, io::*, meta::*, probe::Hint},
-                default::get_probe,
-            };
+///Read a song, along with a `BadTags` error if some of its tags couldn't be read.
+///
+///Damaged tags don't stop the song from being added, they're only reported.
+pub fn read_song(path: &Path) -> Result<(Song, Option<ScanError>), ScanError> {
+    let bad_tags = |message: &str| ScanError::new(path, ErrorKind::BadTags, message);
 
-            let file = match File::open(path) {
-                Ok(file) => file,
-                Err(err) => return Err(format!("Error: ({err}) @ {}", path.to_string_lossy())),
-            };
+    //Fall back to Symphonia for anything the native readers can't handle.
+    //If it can read the file, the native reader probably tripped over the tags.
+    let fallback = |err: &dyn std::fmt::Display| -> Result<_, ScanError> {
+        let message = format!("Read with Symphonia instead: {err}");
+        Ok((probe(path)?, Some(bad_tags(&message))))
+    };
 
-            let mss = MediaSourceStream::new(Box::new(file), MediaSourceStreamOptions::default());
-
-            let mut probe = match get_probe().format(
-                &Hint::new(),
-                mss,
-                &FormatOptions::default(),
-                &MetadataOptions {
-                    limit_visual_bytes: Limit::Maximum(1),
-                    ..Default::default()
-                },
-            ) {
-                Ok(probe) => probe,
-                Err(err) => return Err(format!("Error: ({err}) @ {}", path.to_string_lossy()))?,
+    let Some(extension) = path.extension() else {
+        return Err(ScanError::new(
+            path,
+            ErrorKind::Unsupported,
+            "Path is not audio.",
+        ));
+    };
+    let (song, damage) = match extension.to_str() {
+        Some("flac") => read_metadata(path).map_err(|err| {
+            let kind = match err {
+                FlacError::Io(_) => ErrorKind::Io,
+                FlacError::NotFlac => ErrorKind::Unsupported,
+                FlacError::Truncated
+                | FlacError::MissingStreamInfo
+                | FlacError::InvalidBlock(_) => ErrorKind::Unreadable,
             };
+            ScanError::new(path, kind, err)
+        })?,
+        Some("mp3") => match id3::read_song(path) {
+            Ok(song) => song,
+            Err(err) => return fallback(&err),
+        },
+        Some("ogg") => match ogg::read_song(path) {
+            Ok(song) => song,
+            //Opus and misnamed files aren't a problem with the tags.
+            Err(ogg::OggError::NotOgg | ogg::OggError::NotVorbis) => {
+                return Ok((probe(path)?, None))
+            }
+            Err(err) => return fallback(&err),
+        },
+        _ => return Ok((probe(path)?, None)),
+    };
+    Ok((song, damage.map(bad_tags)))
+}
 
-            let mut title = String::from("Unknown Title");
-            let mut album = String::from("Unknown Album");
-            let mut artist = String::from("Unknown Artist");
-            let mut track_number = 1;
-            let mut disc_number = 1;
-            let mut gain = 0.0;
+///Read a song with Symphonia, slower than the native readers but supports more formats.
+pub fn probe(path: &Path) -> Result<Song, ScanError> {
+    use symphonia::{
+        core::{codecs::*, errors::Error, formats::FormatOptions, io::*, meta::*, probe::Hint},
+        default::get_probe,
+    };
 
-            let mut metadata_revision = probe.format.metadata();
-            let mut metadata = probe.metadata.get();
-            let mut m = None;
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) => return Err(ScanError::new(path, ErrorKind::Io, err)),
+    };
+    let size = file.metadata().map(|m| m.len()).unwrap_or_default();
 
-            if let Some(metadata) = metadata_revision.skip_to_latest() {
-                m = Some(metadata);
+    let mss = MediaSourceStream::new(Box::new(file), MediaSourceStreamOptions::default());
+
+    let mut probe = match get_probe().format(
+        &Hint::new(),
+        mss,
+        &FormatOptions::default(),
+        &MetadataOptions {
+            limit_visual_bytes: Limit::Maximum(1),
+            ..Default::default()
+        },
+    ) {
+        Ok(probe) => probe,
+        Err(err) => {
+            let kind = match err {
+                Error::IoError(_) => ErrorKind::Io,
+                Error::Unsupported(_) => ErrorKind::Unsupported,
+                _ => ErrorKind::Unreadable,
             };
+            return Err(ScanError::new(path, kind, err));
+        }
+    };
 
-            if let Some(metadata) = &mut metadata {
-                if let Some(metadata) = metadata.skip_to_latest() {
-                    m = Some(metadata)
-                };
-            }
+    let mut song = Song::default();
+    song.path = match path.to_str() {
+        Some(path) => path.to_string(),
+        None => {
+            return Err(ScanError::new(
+                path,
+                ErrorKind::Unsupported,
+                "Invalid UTF-8 in path.",
+            ))
+        }
+    };
+    song.track_artist = String::new();
+
+    if let Some(track) = probe.format.default_track() {
+        let params = &track.codec_params;
+        song.codec = match params.codec {
+            CODEC_TYPE_FLAC => "FLAC",
+            CODEC_TYPE_MP3 => "MP3",
+            CODEC_TYPE_VORBIS => "Vorbis",
+            CODEC_TYPE_OPUS => "Opus",
+            _ => "Unknown",
+        }
+        .to_string();
+        song.sample_rate = params.sample_rate.unwrap_or_default();
+        song.bit_depth = params.bits_per_sample.unwrap_or_default() as u8;
+        song.channels = params.channels.map(|c| c.count() as u8).unwrap_or_default();
+        if let (Some(time_base), Some(frames)) = (params.time_base, params.n_frames) {
+            let time = time_base.calc_time(frames);
+            song.duration = Duration::from_secs(time.seconds) + Duration::from_secs_f64(time.frac);
+        }
+        song.bitrate = bitrate(size, song.duration);
+    }
+
+    let mut metadata_revision = probe.format.metadata();
+    let mut metadata = probe.metadata.get();
+    let mut m = None;
 
-            if let Some(metadata) = m {
-                for tag in metadata.tags() {
-                    if let Some(std_key) = tag.std_key {
-                        match std_key {
-                            StandardTagKey::AlbumArtist => artist = tag.value.to_string(),
-                            StandardTagKey::Artist if artist == "Unknown Artist" => {
-                                artist = tag.value.to_string()
-                            }
-                            StandardTagKey::Album => album = tag.value.to_string(),
-                            StandardTagKey::TrackTitle => title = tag.value.to_string(),
-                            StandardTagKey::TrackNumber => {
-                                let num = tag.value.to_string();
-                                if let Some((num, _)) = num.split_once('/') {
-                                    track_number = num.parse().unwrap_or(1);
-                                } else {
-                                    track_number = num.parse().unwrap_or(1);
-                                }
-                            }
-                            StandardTagKey::DiscNumber => {
-                                let num = tag.value.to_string();
-                                if let Some((num, _)) = num.split_once('/') {
-                                    disc_number = num.parse().unwrap_or(1);
-                                } else {
-                                    disc_number = num.parse().unwrap_or(1);
-                                }
-                            }
-                            StandardTagKey::ReplayGainTrackGain => {
-                                let tag = tag.value.to_string();
-                                let (_, value) =
-                                    tag.split_once(' ').ok_or("Invalid replay gain.")?;
-                                let db = value.parse().unwrap_or(0.0);
-                                gain = 10.0f32.powf(db / 20.0);
-                            }
-                            _ => (),
+    if let Some(metadata) = metadata_revision.skip_to_latest() {
+        m = Some(metadata);
+    };
+
+    if let Some(metadata) = &mut metadata {
+        if let Some(metadata) = metadata.skip_to_latest() {
+            m = Some(metadata)
+        };
+    }
+
+    if let Some(metadata) = m {
+        for tag in metadata.tags() {
+            if let Some(std_key) = tag.std_key {
+                match std_key {
+                    StandardTagKey::AlbumArtist => song.artist = tag.value.to_string(),
+                    StandardTagKey::Artist => {
+                        song.track_artist = tag.value.to_string();
+                        if song.artist == UNKNOWN_ARTIST {
+                            song.artist = tag.value.to_string()
                         }
                     }
+                    StandardTagKey::Album => song.album = tag.value.to_string(),
+                    StandardTagKey::TrackTitle => song.title = tag.value.to_string(),
+                    StandardTagKey::TrackNumber => {
+                        song.track_number = parse_number(&tag.value.to_string()).unwrap_or(1)
+                    }
+                    StandardTagKey::DiscNumber => {
+                        song.disc_number = parse_number(&tag.value.to_string()).unwrap_or(1)
+                    }
+                    StandardTagKey::ReplayGainTrackGain => {
+                        song.gain = parse_gain(&tag.value.to_string()).unwrap_or(0.0)
+                    }
+                    StandardTagKey::Genre => push_genre(&mut song.genre, &tag.value.to_string()),
+                    StandardTagKey::Composer => song.composer = tag.value.to_string(),
+                    StandardTagKey::Date | StandardTagKey::ReleaseDate if song.year == 0 => {
+                        song.year = parse_year(&tag.value.to_string()).unwrap_or_default()
+                    }
+                    _ => (),
                 }
             }
+        }
+    }
+
+    if song.track_artist.is_empty() {
+        song.track_artist = song.artist.clone();
+    }
+
+    Ok(song)
+}
+
+//Split a path into the directory (with the trailing separator) and the file name.
+fn split_path(path: &str) -> (&str, &str) {
+    match path.rfind(['/', '\\']) {
+        Some(i) => path.split_at(i + 1),
+        None => ("", path),
+    }
+}
+
+#[derive(Default)]
+struct Strings<'a> {
+    indices: HashMap<&'a str, u32>,
+    table: Vec<&'a str>,
+}
+
+impl<'a> Strings<'a> {
+    fn intern(&mut self, s: &'a str) -> u32 {
+        *self.indices.entry(s).or_insert_with(|| {
+            self.table.push(s);
+            self.table.len() as u32 - 1
+        })
+    }
+}
 
-            Ok(Song {
-                title,
-                album,
-                artist,
-                disc_number,
-                track_number,
-                path: path.to_str().ok_or("Invalid UTF-8 in path.")?.to_string(),
-                gain,
+///A song and the state of it's file when it was scanned.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub song: Song,
+    pub size: u64,
+    ///Nanoseconds since the unix epoch.
+    pub modified: u64,
+}
+
+fn stamp(path: &Path) -> Option<(u64, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some((metadata.len(), modified.as_nanos() as u64))
+}
+
+///Encode songs using the current database version.
+pub fn to_bytes(entries: &[Entry]) -> Vec<u8> {
+    let mut strings = Strings::default();
+    let mut body = Vec::with_capacity(entries.len() * 74);
+
+    for Entry {
+        song,
+        size,
+        modified,
+    } in entries
+    {
+        let (directory, file) = split_path(&song.path);
+        for s in [&song.title, &song.album, &song.artist] {
+            body.extend(strings.intern(s).to_le_bytes());
+        }
+        body.extend(song.disc_number.to_le_bytes());
+        body.extend(song.track_number.to_le_bytes());
+        body.extend(strings.intern(directory).to_le_bytes());
+        body.extend(strings.intern(file).to_le_bytes());
+        body.extend(song.gain.to_le_bytes());
+        body.extend(size.to_le_bytes());
+        body.extend(modified.to_le_bytes());
+        for s in [&song.track_artist, &song.genre, &song.composer, &song.codec] {
+            body.extend(strings.intern(s).to_le_bytes());
+        }
+        body.extend(song.year.to_le_bytes());
+        body.extend((song.duration.as_millis() as u32).to_le_bytes());
+        body.extend(song.sample_rate.to_le_bytes());
+        body.push(song.bit_depth);
+        body.push(song.channels);
+        body.extend(song.bitrate.to_le_bytes());
+    }
+
+    let mut bytes = Vec::with_capacity(body.len() + strings.table.len() * 24);
+    bytes.extend(MAGIC);
+    bytes.extend(VERSION.to_le_bytes());
+    bytes.extend((strings.table.len() as u32).to_le_bytes());
+    for s in &strings.table {
+        bytes.extend((s.len() as u32).to_le_bytes());
+        bytes.extend(s.as_bytes());
+    }
+    bytes.extend((entries.len() as u32).to_le_bytes());
+    bytes.extend(body);
+    bytes
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Box<dyn Error>> {
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos + len)
+            .ok_or("Unexpected end of database")?;
+        self.pos += len;
+        Ok(bytes)
+    }
+    fn u8(&mut self) -> Result<u8, Box<dyn Error>> {
+        Ok(self.take(1)?[0])
+    }
+    fn u16(&mut self) -> Result<u16, Box<dyn Error>> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into()?))
+    }
+    fn u32(&mut self) -> Result<u32, Box<dyn Error>> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+    fn u64(&mut self) -> Result<u64, Box<dyn Error>> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+    fn f32(&mut self) -> Result<f32, Box<dyn Error>> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+}
+
+fn read_binary(reader: &mut Reader) -> Result<Vec<Entry>, Box<dyn Error>> {
+    let count = reader.u32()? as usize;
+    let mut table = Vec::with_capacity(count.min(reader.bytes.len()));
+    for _ in 0..count {
+        let len = reader.u32()? as usize;
+        table.push(std::str::from_utf8(reader.take(len)?)?);
+    }
+
+    let string = |index: u32| -> Result<&str, Box<dyn Error>> {
+        Ok(table.get(index as usize).ok_or("Invalid string index")?)
+    };
+
+    let count = reader.u32()? as usize;
+    let mut entries = Vec::with_capacity(count.min(reader.bytes.len()));
+    for _ in 0..count {
+        let title = string(reader.u32()?)?.to_string();
+        let album = string(reader.u32()?)?.to_string();
+        let artist = string(reader.u32()?)?.to_string();
+        let disc_number = reader.u16()?;
+        let track_number = reader.u16()?;
+        let directory = string(reader.u32()?)?;
+        let file = string(reader.u32()?)?;
+        let gain = reader.f32()?;
+        let size = reader.u64()?;
+        let modified = reader.u64()?;
+        let song = Song {
+            title,
+            album,
+            artist,
+            disc_number,
+            track_number,
+            path: format!("{directory}{file}"),
+            gain,
+            track_artist: string(reader.u32()?)?.to_string(),
+            genre: string(reader.u32()?)?.to_string(),
+            composer: string(reader.u32()?)?.to_string(),
+            codec: string(reader.u32()?)?.to_string(),
+            year: reader.u16()?,
+            duration: Duration::from_millis(reader.u32()? as u64),
+            sample_rate: reader.u32()?,
+            bit_depth: reader.u8()?,
+            channels: reader.u8()?,
+            bitrate: reader.u32()?,
+        };
+        entries.push(Entry {
+            song,
+            size,
+            modified,
+        });
+    }
+    Ok(entries)
+}
+
+///Decode songs from the binary or text format.
+///
+///Songs from the text format will have a size and modification time of zero.
+pub fn from_bytes(bytes: &[u8]) -> Result<Vec<Entry>, Box<dyn Error>> {
+    if bytes.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    //Version 0 is plain text.
+    if !bytes.starts_with(MAGIC) {
+        return std::str::from_utf8(bytes)?
+            .lines()
+            .map(|line| {
+                Ok(Entry {
+                    song: Song::deserialize(line)?,
+                    size: 0,
+                    modified: 0,
+                })
             })
-        } else {
-            read_metadata(path)
-                .map_err(|err| format!("Error: ({err}) @ {}", path.to_string_lossy()))
+            .collect();
+    }
+
+    let mut reader = Reader { bytes, pos: 4 };
+    match reader.u16()? {
+        VERSION => read_binary(&mut reader),
+        version => Err(format!(
+            "Database version {version} is newer than this version of gonk ({VERSION})."
+        ))?,
+    }
+}
+
+///Read the database from disk, migrating it to the current version if needed.
+pub fn read_entries() -> Result<Vec<Entry>, Box<dyn Error>> {
+    let bytes = match fs::read(database_path()) {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err)?,
+    };
+    let entries = from_bytes(&bytes)?;
+
+    let current = bytes.starts_with(MAGIC) && bytes.get(4..6) == Some(&VERSION.to_le_bytes());
+    if !current && !entries.is_empty() {
+        write(&entries)?;
+    }
+
+    Ok(entries)
+}
+
+///Songs migrated from the text format, they need to be scanned again
+///to fill in the fields it didn't store.
+pub fn unscanned() -> Vec<PathBuf> {
+    read_entries()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|entry| entry.modified == 0)
+        .map(|entry| PathBuf::from(entry.song.path))
+        .collect()
+}
+
+pub fn read() -> Result<Vec<Song>, Box<dyn Error>> {
+    Ok(read_entries()?
+        .into_iter()
+        .map(|entry| entry.song)
+        .collect())
+}
+
+///Replace the database on disk.
+pub fn write(entries: &[Entry]) -> io::Result<()> {
+    //Each write gets it's own file so two writers can't mix their data.
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let count = COUNT.fetch_add(1, Ordering::Relaxed);
+    let mut path = database_path().to_path_buf();
+    path.pop();
+    path.push(format!("temp-{}-{count}.db", std::process::id()));
+
+    let mut writer = BufWriter::new(File::create(&path)?);
+    writer.write_all(&to_bytes(entries))?;
+    writer.flush()?;
+    drop(writer);
+
+    fs::rename(path, database_path())
+}
+
+///What changed since the last scan.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Changes {
+    pub added: usize,
+    pub removed: usize,
+    pub updated: usize,
+}
+
+impl std::fmt::Display for Changes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} added, {} removed, {} updated",
+            self.added, self.removed, self.updated
+        )
+    }
+}
+
+///Why a file couldn't be added to the database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ErrorKind {
+    ///The file is corrupt or truncated.
+    Unreadable,
+    ///Not a supported format, e.g. Opus in an `.ogg`.
+    Unsupported,
+    BadTags,
+    Io,
+}
+
+impl ErrorKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            ErrorKind::Unreadable => "Unreadable",
+            ErrorKind::Unsupported => "Unsupported",
+            ErrorKind::BadTags => "Bad tags",
+            ErrorKind::Io => "IO",
+        }
+    }
+}
+
+impl FromStr for ErrorKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Unreadable" => Ok(ErrorKind::Unreadable),
+            "Unsupported" => Ok(ErrorKind::Unsupported),
+            "Bad tags" => Ok(ErrorKind::BadTags),
+            "IO" => Ok(ErrorKind::Io),
+            _ => Err(format!("Unknown error kind {s}")),
         }
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanError {
+    pub path: String,
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl ScanError {
+    pub fn new(path: &Path, kind: ErrorKind, message: impl ToString) -> Self {
+        Self {
+            path: path.to_string_lossy().to_string(),
+            kind,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for ScanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} @ {}", self.kind.label(), self.message, self.path)
+    }
+}
+
+impl Serialize for ScanError {
+    fn serialize(&self) -> String {
+        format!(
+            "{}\t{}\t{}\n",
+            self.kind.label(),
+            escape(&self.path),
+            escape(&self.message)
+        )
+    }
+}
+
+impl Deserialize for ScanError {
+    type Error = Box<dyn std::error::Error>;
+
+    fn deserialize(s: &str) -> Result<Self, Self::Error> {
+        let mut parts = s.trim_end_matches('\n').splitn(3, '\t');
+        Ok(ScanError {
+            kind: parts.next().ok_or("Missing kind")?.parse()?,
+            path: parts.next().ok_or("Missing path")?.to_string(),
+            message: parts.next().unwrap_or_default().to_string(),
+        })
+    }
+}
+
 #[derive(Debug)]
 pub enum ScanResult {
-    Completed,
-    CompletedWithErrors(Vec<String>),
+    Completed(Changes),
+    CompletedWithErrors(Changes, Vec<ScanError>),
     FileInUse,
 }
 
+///Files that failed to scan, kept until they're fixed, removed or ignored.
+pub fn errors_path() -> PathBuf {
+    gonk_path().join("scan_errors.txt")
+}
+
+///Read the error report, lines that can't be read are skipped.
+pub fn read_errors() -> Vec<ScanError> {
+    fs::read_to_string(errors_path())
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| ScanError::deserialize(line).ok())
+        .collect()
+}
+
+pub fn write_errors(errors: &[ScanError]) -> io::Result<()> {
+    let report: String = errors.iter().map(ScanError::serialize).collect();
+    fs::write(errors_path(), report)
+}
+
 pub fn reset() -> Result<(), Box<dyn Error>> {
     fs::remove_file(settings_path())?;
     if database_path().exists() {
         fs::remove_file(database_path())?;
     }
+    art::clear()?;
     Ok(())
 }
 
-pub fn create(path: &str) -> JoinHandle<ScanResult> {
-    let path = path.to_string();
+//Songs with damaged tags are still added, the error is only for the report.
+enum Scanned {
+    Unchanged(Entry),
+    Updated(Entry, Option<ScanError>),
+    Added(Entry, Option<ScanError>),
+    Failed(ScanError),
+}
+
+pub fn is_audio(path: &Path) -> bool {
+    match path.extension() {
+        Some(ex) => matches!(ex.to_str(), Some("flac" | "mp3" | "ogg")),
+        None => false,
+    }
+}
+
+fn audio_files(path: &Path, options: &walk::Options) -> Vec<String> {
+    walk::walk(path, options)
+        .into_iter()
+        .filter(|path| is_audio(path))
+        .filter_map(|path| path.to_str().map(str::to_string))
+        .collect()
+}
+
+//Only read the file if it's new or has changed since the last scan.
+fn scan(path: &str, existing: Option<&Entry>) -> Scanned {
+    let (size, modified) = stamp(Path::new(path)).unwrap_or_default();
+
+    if let Some(existing) = existing {
+        if existing.size == size && existing.modified == modified && size != 0 {
+            return Scanned::Unchanged(existing.clone());
+        }
+    }
+
+    match read_song(Path::new(path)) {
+        Ok((song, damage)) => {
+            let entry = Entry {
+                song,
+                size,
+                modified,
+            };
+            if existing.is_some() {
+                Scanned::Updated(entry, damage)
+            } else {
+                Scanned::Added(entry, damage)
+            }
+        }
+        Err(err) => Scanned::Failed(err),
+    }
+}
+
+///Scan the library folders and update the database.
+///
+///Only files that are new or have a different size or modification time are read.
+///Songs that aren't in any of the folders are removed.
+pub fn create(folders: &[String], options: walk::Options) -> JoinHandle<ScanResult> {
+    let folders = folders.to_vec();
     thread::spawn(move || {
-        let mut db_path = database_path().to_path_buf();
-        db_path.pop();
-        db_path.push("temp.db");
-
-        match File::create(&db_path) {
-            Ok(file) => {
-                let paths: Vec<winwalk::DirEntry> = winwalk::walkdir(path, 0)
-                    .into_iter()
-                    .flatten()
-                    .filter(|entry| match entry.extension() {
-                        Some(ex) => {
-                            matches!(ex.to_str(), Some("flac" | "mp3" | "ogg"))
-                        }
-                        None => false,
-                    })
-                    .collect();
+        //Don't replace a database that can't be read, it might be from a newer version.
+        let old: HashMap<String, Entry> = match read_entries() {
+            Ok(entries) => entries
+                .into_iter()
+                .map(|entry| (entry.song.path.clone(), entry))
+                .collect(),
+            Err(_) => return ScanResult::FileInUse,
+        };
 
-                let songs: Vec<_> = paths
-                    .into_par_iter()
-                    .map(|entry| Song::try_from(Path::new(&entry.path)))
-                    .collect();
+        let mut files: Vec<String> = folders
+            .iter()
+            .flat_map(|f| audio_files(Path::new(f), &options))
+            .collect();
+        //Folders can be nested inside each other.
+        files.sort();
+        files.dedup();
 
-                let errors: Vec<String> = songs
-                    .iter()
-                    .filter_map(|song| {
-                        if let Err(err) = song {
-                            Some(err.clone())
-                        } else {
-                            None
-                        }
-                    })
-                    .collect();
+        let scanned: Vec<Scanned> = files
+            .into_par_iter()
+            .map(|path| scan(&path, old.get(&path)))
+            .collect();
 
-                let songs: Vec<Song> = songs.into_iter().flatten().collect();
-                let mut writer = BufWriter::new(&file);
-                writer.write_all(&songs.serialize().into_bytes()).unwrap();
-                writer.flush().unwrap();
+        //Files that aren't read again keep their damaged tags.
+        let mut bad_tags: HashMap<String, ScanError> = read_errors()
+            .into_iter()
+            .filter(|error| error.kind == ErrorKind::BadTags)
+            .map(|error| (error.path.clone(), error))
+            .collect();
+
+        let mut changes = Changes::default();
+        let mut entries = Vec::with_capacity(scanned.len());
+        let mut errors = Vec::new();
+        let mut kept = 0;
 
-                //Remove old database and replace it with new.
-                fs::rename(db_path, database_path()).unwrap();
+        for scanned in scanned {
+            match scanned {
+                Scanned::Unchanged(entry) => {
+                    kept += 1;
+                    errors.extend(bad_tags.remove(&entry.song.path));
+                    entries.push(entry);
+                }
+                Scanned::Updated(entry, damage) => {
+                    kept += 1;
+                    changes.updated += 1;
+                    errors.extend(damage);
+                    entries.push(entry);
+                }
+                Scanned::Added(entry, damage) => {
+                    changes.added += 1;
+                    errors.extend(damage);
+                    entries.push(entry);
+                }
+                Scanned::Failed(err) => errors.push(err),
+            }
+        }
 
-                // let _db = vdb::create().unwrap();
+        //Anything that wasn't found or can't be read anymore.
+        changes.removed = old.len() - kept;
 
-                if errors.is_empty() {
-                    ScanResult::Completed
-                } else {
-                    ScanResult::CompletedWithErrors(errors)
+        if write(&entries).is_err() {
+            return ScanResult::FileInUse;
+        }
+
+        //The report is only for the user, the scan still succeeded.
+        errors.sort_by(|a, b| a.path.cmp(&b.path));
+        let _ = write_errors(&errors);
+
+        if errors.is_empty() {
+            ScanResult::Completed(changes)
+        } else {
+            ScanResult::CompletedWithErrors(changes, errors)
+        }
+    })
+}
+
+///Apply changes to files or folders to the database, see `watch::Watcher`.
+///
+///Paths that exist are scanned, anything under a path that no longer exists is removed.
+///Paths outside of the library folders or excluded by the walk options are ignored.
+pub fn update(
+    paths: Vec<PathBuf>,
+    folders: &[String],
+    options: walk::Options,
+) -> JoinHandle<ScanResult> {
+    let folders = folders.to_vec();
+    thread::spawn(move || {
+        let mut songs: HashMap<String, Entry> = match read_entries() {
+            Ok(entries) => entries
+                .into_iter()
+                .map(|entry| (entry.song.path.clone(), entry))
+                .collect(),
+            Err(_) => return ScanResult::FileInUse,
+        };
+
+        let mut changes = Changes::default();
+        let mut files = Vec::new();
+
+        for path in &paths {
+            songs.retain(|key, _| {
+                let key = Path::new(key);
+                let removed = key.starts_with(path) && !key.exists();
+                if removed {
+                    changes.removed += 1;
+                }
+                !removed
+            });
+
+            let Some(root) = folders.iter().map(Path::new).find(|f| path.starts_with(f)) else {
+                continue;
+            };
+            if walk::excluded(root, path, &options) {
+                continue;
+            }
+
+            if path.is_dir() {
+                //Rules from parent folders also apply.
+                files.extend(
+                    audio_files(path, &options)
+                        .into_iter()
+                        .filter(|file| !walk::excluded(root, Path::new(file), &options)),
+                );
+            } else if path.is_file() && is_audio(path) {
+                files.push(path.to_string_lossy().to_string());
+            }
+        }
+        files.sort();
+        files.dedup();
+
+        let scanned: Vec<Scanned> = files
+            .into_par_iter()
+            .map(|path| scan(&path, songs.get(&path)))
+            .collect();
+
+        let mut errors = Vec::new();
+        let mut unchanged = HashSet::new();
+        for scanned in scanned {
+            match scanned {
+                Scanned::Unchanged(entry) => {
+                    unchanged.insert(entry.song.path);
+                }
+                Scanned::Updated(entry, damage) => {
+                    changes.updated += 1;
+                    errors.extend(damage);
+                    songs.insert(entry.song.path.clone(), entry);
                 }
+                Scanned::Added(entry, damage) => {
+                    changes.added += 1;
+                    errors.extend(damage);
+                    songs.insert(entry.song.path.clone(), entry);
+                }
+                Scanned::Failed(err) => errors.push(err),
+            }
+        }
+
+        if changes != Changes::default() {
+            let entries: Vec<Entry> = songs.into_values().collect();
+            if write(&entries).is_err() {
+                return ScanResult::FileInUse;
             }
-            Err(_) => ScanResult::FileInUse,
+        }
+
+        //Errors for files that were rescanned or removed are replaced.
+        //Files that weren't read again keep their damaged tags.
+        let mut report = read_errors();
+        let len = report.len();
+        report.retain(|error| {
+            (error.kind == ErrorKind::BadTags && unchanged.contains(&error.path))
+                || !paths
+                    .iter()
+                    .any(|path| Path::new(&error.path).starts_with(path))
+        });
+        if report.len() != len || !errors.is_empty() {
+            report.extend(errors.iter().cloned());
+            report.sort_by(|a, b| a.path.cmp(&b.path));
+            let _ = write_errors(&report);
+        }
+
+        if errors.is_empty() {
+            ScanResult::Completed(changes)
+        } else {
+            ScanResult::CompletedWithErrors(changes, errors)
         }
     })
 }
 
 #[cfg(test)]
 mod tests {
-    use std::{str::from_utf8_unchecked, time::Duration};
+    use std::time::Duration;
 
     use super::*;
 
@@ -325,6 +1070,106 @@ mod tests {
         let song = Song::example();
         let string = song.serialize();
         assert_eq!(Song::deserialize(&string).unwrap(), song);
+
+        //Songs saved by older versions.
+        let old = Song::deserialize("title\talbum\tartist\t1\t2\tpath\t0.0").unwrap();
+        assert_eq!(old.track_artist, "artist");
+        assert_eq!(old.duration, Duration::ZERO);
+    }
+
+    #[test]
+    fn errors() {
+        let error = ScanError::new(
+            Path::new("music/song.ogg"),
+            ErrorKind::Unsupported,
+            "Stream is not Vorbis.",
+        );
+        let string = error.serialize();
+        assert_eq!(
+            string,
+            "Unsupported\tmusic/song.ogg\tStream is not Vorbis.\n"
+        );
+        assert_eq!(ScanError::deserialize(&string).unwrap(), error);
+        assert_eq!(
+            error.to_string(),
+            "Unsupported: Stream is not Vorbis. @ music/song.ogg"
+        );
+        assert!(ScanError::deserialize("Corrupt\tmusic/song.ogg").is_err());
+
+        let error = Song::try_from(Path::new("missing.flac")).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Io);
+    }
+
+    #[test]
+    fn bad_tags() {
+        //An empty STREAMINFO and a comment block that claims more comments than it has.
+        let mut comments = 0u32.to_le_bytes().to_vec();
+        comments.extend(2u32.to_le_bytes());
+        comments.extend(10u32.to_le_bytes());
+        comments.extend(b"TITLE=Song");
+        let mut bytes = b"fLaC\x00\x00\x00\x22".to_vec();
+        bytes.extend([0; 34]);
+        bytes.extend([0x84, 0, 0, comments.len() as u8]);
+        bytes.extend(comments);
+
+        let path = std::env::temp_dir().join("gonk_bad_tags.flac");
+        fs::write(&path, bytes).unwrap();
+        let (song, damage) = read_song(&path).unwrap();
+        assert_eq!(song.title, "Song");
+        assert_eq!(damage.unwrap().kind, ErrorKind::BadTags);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn tags() {
+        assert_eq!(parse_year("2001"), Some(2001));
+        assert_eq!(parse_year("2001-05-12"), Some(2001));
+        assert_eq!(parse_year("12/05/2001"), Some(2001));
+        assert_eq!(parse_year("Unknown"), None);
+
+        let mut genre = String::new();
+        push_genre(&mut genre, "Jazz");
+        push_genre(&mut genre, " Fusion ");
+        push_genre(&mut genre, "Jazz");
+        assert_eq!(genre, "Jazz; Fusion");
+
+        assert_eq!(bitrate(4_000_000, Duration::from_secs(32)), 1000);
+        assert_eq!(bitrate(4_000_000, Duration::ZERO), 0);
+    }
+
+    #[test]
+    fn binary() {
+        let mut other = Song::example();
+        other.title = "other".to_string();
+        other.path = r"D:\Music\Album\02.flac".to_string();
+        let entries = vec![
+            Entry {
+                song: Song::example(),
+                size: 1024,
+                modified: 1_700_000_000_000_000_000,
+            },
+            Entry {
+                song: other,
+                size: 2048,
+                modified: 0,
+            },
+        ];
+
+        let bytes = to_bytes(&entries);
+        assert!(bytes.starts_with(MAGIC));
+        assert_eq!(from_bytes(&bytes).unwrap(), entries);
+
+        //Text databases are migrated without the file information.
+        let songs: Vec<Song> = entries.iter().map(|entry| entry.song.clone()).collect();
+        let text = from_bytes(songs.serialize().as_bytes()).unwrap();
+        assert_eq!(text[1].song, songs[1]);
+        assert_eq!(text[1].size, 0);
+
+        assert!(from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+        let mut newer = bytes.clone();
+        newer[4..6].copy_from_slice(&(VERSION + 1).to_le_bytes());
+        assert!(from_bytes(&newer).is_err());
     }
 
     #[test]
@@ -337,17 +1182,12 @@ mod tests {
 
     #[test]
     fn database() {
-        let handle = create("D:\\OneDrive\\Music");
+        let handle = create(&["D:\\OneDrive\\Music".to_string()], Default::default());
 
         while !handle.is_finished() {
             thread::sleep(Duration::from_millis(1));
         }
         handle.join().unwrap();
-        let bytes = fs::read(database_path()).unwrap();
-        let db: Result<Vec<Song>, Box<dyn Error>> = unsafe { from_utf8_unchecked(&bytes) }
-            .lines()
-            .map(Song::deserialize)
-            .collect();
-        let _ = db.unwrap();
+        let _ = read().unwrap();
     }
 }
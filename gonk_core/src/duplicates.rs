@@ -0,0 +1,153 @@
+//! Find songs that are likely the same track
+//!
+//! Songs are grouped by artist, album and title after removing case, punctuation and spacing.
+//! Songs in a group are only duplicates if their durations are close,
+//! so different versions with the same name are kept apart.
+use crate::Song;
+use std::{collections::HashMap, time::Duration};
+
+///Encoders can add or remove a little silence.
+pub const TOLERANCE: Duration = Duration::from_secs(2);
+
+///Lower case letters and numbers only, `The Beatles!` becomes `thebeatles`.
+pub fn normalise(s: &str) -> String {
+    s.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn close(a: Duration, b: Duration) -> bool {
+    a.abs_diff(b) <= TOLERANCE
+}
+
+///Groups of two or more songs that are likely duplicates, sorted by artist, album and title.
+pub fn find(songs: &[Song]) -> Vec<Vec<Song>> {
+    let mut names: HashMap<(String, String, String), Vec<&Song>> = HashMap::new();
+    for song in songs {
+        let key = (
+            normalise(&song.artist),
+            normalise(&song.album),
+            normalise(&song.title),
+        );
+        names.entry(key).or_default().push(song);
+    }
+
+    let mut groups: Vec<Vec<Song>> = Vec::new();
+    for (_, songs) in names {
+        if songs.len() < 2 {
+            continue;
+        }
+
+        let (unknown, mut known): (Vec<&Song>, Vec<&Song>) =
+            songs.into_iter().partition(|song| song.duration.is_zero());
+        known.sort_by_key(|song| song.duration);
+
+        let mut runs: Vec<Vec<&Song>> = Vec::new();
+        for song in known {
+            match runs.last_mut() {
+                Some(run) if close(run[run.len() - 1].duration, song.duration) => run.push(song),
+                _ => runs.push(vec![song]),
+            }
+        }
+        if runs.is_empty() {
+            runs.push(Vec::new());
+        }
+
+        //Unknown durations match anything, so they're added to every group.
+        for mut run in runs {
+            run.extend(&unknown);
+            if run.len() > 1 {
+                groups.push(run.into_iter().cloned().collect());
+            }
+        }
+    }
+
+    for group in &mut groups {
+        group.sort_by(|a, b| a.path.cmp(&b.path));
+    }
+    groups.sort_by_cached_key(|group| {
+        let song = &group[0];
+        (
+            song.artist.to_lowercase(),
+            song.album.to_lowercase(),
+            song.title.to_lowercase(),
+        )
+    });
+    groups
+}
+
+///Format, sample rate, bit depth and bitrate, e.g. `FLAC 44.1 kHz 16-bit 912 kbps`.
+pub fn describe(song: &Song) -> String {
+    let mut parts = Vec::new();
+    if !song.codec.is_empty() {
+        parts.push(song.codec.clone());
+    }
+    if song.sample_rate != 0 {
+        parts.push(format!("{} kHz", song.sample_rate as f32 / 1000.0));
+    }
+    if song.bit_depth != 0 {
+        parts.push(format!("{}-bit", song.bit_depth));
+    }
+    if song.bitrate != 0 {
+        parts.push(format!("{} kbps", song.bitrate));
+    }
+    parts.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn song(title: &str, path: &str, secs: u64) -> Song {
+        Song {
+            title: title.to_string(),
+            album: "Album".to_string(),
+            artist: "Artist".to_string(),
+            path: path.to_string(),
+            duration: Duration::from_secs(secs),
+            ..Song::default()
+        }
+    }
+
+    #[test]
+    fn duplicates() {
+        assert_eq!(normalise("The Beatles!"), "thebeatles");
+        assert_eq!(normalise("Don't  Stop"), normalise("dont stop"));
+
+        let songs = [
+            song("Song", "a.flac", 200),
+            song("song.", "b.mp3", 201),
+            //Extended version.
+            song("Song", "c.flac", 400),
+            song("Other", "d.flac", 200),
+            song("Other", "e.flac", 0),
+        ];
+        let groups = find(&songs);
+        assert_eq!(groups.len(), 2);
+        let paths: Vec<&str> = groups[1].iter().map(|song| song.path.as_str()).collect();
+        assert_eq!(paths, ["a.flac", "b.mp3"]);
+        assert_eq!(groups[0].len(), 2);
+
+        //A song without a duration could be either version.
+        let songs = [
+            song("Live", "a.flac", 200),
+            song("Live", "b.flac", 400),
+            song("Live", "c.mp3", 0),
+        ];
+        let groups = find(&songs);
+        assert_eq!(groups.len(), 2);
+        assert!(groups.iter().all(|group| group[1].path == "c.mp3"));
+        assert_eq!(
+            find(&[song("Live", "a.flac", 0), song("Live", "b.flac", 0)]).len(),
+            1
+        );
+
+        let mut flac = song("Song", "a.flac", 200);
+        flac.codec = "FLAC".to_string();
+        flac.sample_rate = 44100;
+        flac.bit_depth = 16;
+        flac.bitrate = 912;
+        assert_eq!(describe(&flac), "FLAC 44.1 kHz 16-bit 912 kbps");
+    }
+}
@@ -0,0 +1,712 @@
+//! Write tags back to files
+//!
+//! FLAC files get new Vorbis comments and MP3 files get a new ID3v2 tag.
+//! When the tags fit in the space used by the old ones, only the start of the file is rewritten.
+//! Otherwise a copy with fresh padding is written to a temporary file which replaces the original.
+use crate::{
+    id3::{self, remove_unsync, syncsafe},
+    FlacError, Song,
+};
+use std::{
+    error::Error,
+    fmt,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    thread::{self, JoinHandle},
+};
+
+//Room for future edits when the file has to be copied.
+const PADDING: usize = 4096;
+
+#[derive(Debug)]
+pub enum TagError {
+    Io(io::Error),
+    Flac(FlacError),
+    ///Only FLAC and MP3 files with ID3v2.3 or ID3v2.4 tags can be written,
+    ///and only if the existing tags can be read completely.
+    Unsupported,
+    ///A number field has a value that isn't a number.
+    InvalidValue(&'static str),
+    ///The tags don't fit in a metadata block or frame.
+    TooLarge,
+}
+
+impl fmt::Display for TagError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TagError::Io(err) => write!(f, "{err}"),
+            TagError::Flac(err) => write!(f, "{err}"),
+            TagError::Unsupported => write!(f, "Writing tags to this file is not supported."),
+            TagError::InvalidValue(field) => write!(f, "{field} must be a number."),
+            TagError::TooLarge => write!(f, "Tags are too large."),
+        }
+    }
+}
+
+impl Error for TagError {}
+
+impl From<io::Error> for TagError {
+    fn from(err: io::Error) -> Self {
+        TagError::Io(err)
+    }
+}
+
+impl From<FlacError> for TagError {
+    fn from(err: FlacError) -> Self {
+        TagError::Flac(err)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Field {
+    Title,
+    ///Album artist.
+    Artist,
+    TrackArtist,
+    Album,
+    TrackNumber,
+    DiscNumber,
+    Year,
+    Genre,
+}
+
+impl Field {
+    pub fn label(self) -> &'static str {
+        match self {
+            Field::Title => "Title",
+            Field::Artist => "Album artist",
+            Field::TrackArtist => "Artist",
+            Field::Album => "Album",
+            Field::TrackNumber => "Track",
+            Field::DiscNumber => "Disc",
+            Field::Year => "Year",
+            Field::Genre => "Genre",
+        }
+    }
+
+    ///The value as it would be shown in the editor.
+    pub fn value(self, song: &Song) -> String {
+        match self {
+            Field::Title => song.title.clone(),
+            Field::Artist => song.artist.clone(),
+            Field::TrackArtist => song.track_artist.clone(),
+            Field::Album => song.album.clone(),
+            Field::TrackNumber => song.track_number.to_string(),
+            Field::DiscNumber => song.disc_number.to_string(),
+            Field::Year if song.year == 0 => String::new(),
+            Field::Year => song.year.to_string(),
+            Field::Genre => song.genre.clone(),
+        }
+    }
+
+    //The first key is written, every key is removed.
+    fn vorbis(self) -> &'static [&'static str] {
+        match self {
+            Field::Title => &["TITLE"],
+            Field::Artist => &["ALBUMARTIST", "ALBUM ARTIST"],
+            Field::TrackArtist => &["ARTIST"],
+            Field::Album => &["ALBUM"],
+            Field::TrackNumber => &["TRACKNUMBER"],
+            Field::DiscNumber => &["DISCNUMBER"],
+            Field::Year => &["DATE", "YEAR"],
+            Field::Genre => &["GENRE"],
+        }
+    }
+
+    fn id3(self, version: u8) -> &'static [&'static str] {
+        match self {
+            Field::Title => &["TIT2"],
+            Field::Artist => &["TPE2"],
+            Field::TrackArtist => &["TPE1"],
+            Field::Album => &["TALB"],
+            Field::TrackNumber => &["TRCK"],
+            Field::DiscNumber => &["TPOS"],
+            Field::Year if version == 4 => &["TDRC", "TYER"],
+            Field::Year => &["TYER", "TDRC"],
+            Field::Genre => &["TCON"],
+        }
+    }
+
+    fn is_number(self) -> bool {
+        matches!(self, Field::TrackNumber | Field::DiscNumber | Field::Year)
+    }
+}
+
+//Genres are joined with `; ` in the database but stored separately.
+fn values(field: Field, value: &str) -> Vec<String> {
+    let value = value.trim();
+    if field == Field::Genre {
+        value
+            .split(';')
+            .map(str::trim)
+            .filter(|genre| !genre.is_empty())
+            .map(str::to_string)
+            .collect()
+    } else if value.is_empty() {
+        Vec::new()
+    } else {
+        vec![value.to_string()]
+    }
+}
+
+///Write the changes to a FLAC or MP3 file, empty values remove the tag.
+pub fn write(path: &Path, changes: &[(Field, String)]) -> Result<(), TagError> {
+    for (field, value) in changes {
+        let value = value.trim();
+        if field.is_number() && !value.is_empty() && value.parse::<u16>().is_err() {
+            return Err(TagError::InvalidValue(field.label()));
+        }
+    }
+
+    match path.extension().and_then(|ex| ex.to_str()) {
+        Some("flac") => write_flac(path, changes),
+        Some("mp3") => write_id3(path, changes),
+        _ => Err(TagError::Unsupported),
+    }
+}
+
+///Write each file on another thread, see `write`.
+pub fn write_all(
+    edits: Vec<(PathBuf, Vec<(Field, String)>)>,
+) -> JoinHandle<Vec<(PathBuf, Result<(), TagError>)>> {
+    thread::spawn(move || {
+        edits
+            .into_iter()
+            .map(|(path, changes)| {
+                let result = write(&path, &changes);
+                (path, result)
+            })
+            .collect()
+    })
+}
+
+//Copy the audio after `offset` into a new file that starts with `header`.
+fn replace(path: &Path, header: &[u8], offset: u64) -> io::Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".gonk");
+    let temp = PathBuf::from(temp);
+
+    let result = (|| -> io::Result<()> {
+        let mut source = File::open(path)?;
+        source.seek(SeekFrom::Start(offset))?;
+        let mut writer = BufWriter::new(File::create(&temp)?);
+        writer.write_all(header)?;
+        io::copy(&mut source, &mut writer)?;
+        writer.into_inner()?.sync_all()?;
+        fs::set_permissions(&temp, source.metadata()?.permissions())
+    })();
+
+    match result {
+        Ok(()) => fs::rename(&temp, path),
+        Err(err) => {
+            let _ = fs::remove_file(&temp);
+            Err(err)
+        }
+    }
+}
+
+//The new header is exactly the same size as the old one.
+fn overwrite(path: &Path, header: &[u8]) -> io::Result<()> {
+    let mut file = File::options().write(true).open(path)?;
+    file.write_all(header)?;
+    file.sync_all()
+}
+
+//Vorbis comments are kept as they are, unlike `vorbis_comments`.
+//Only the edited fields are replaced, everything else is written back byte for byte.
+#[derive(Default)]
+struct RawComments {
+    vendor: Vec<u8>,
+    entries: Vec<Vec<u8>>,
+}
+
+fn take<'a>(data: &'a [u8], pos: &mut usize, len: usize) -> Option<&'a [u8]> {
+    let bytes = data.get(*pos..*pos + len)?;
+    *pos += len;
+    Some(bytes)
+}
+
+fn take_string<'a>(data: &'a [u8], pos: &mut usize) -> Option<&'a [u8]> {
+    let len = u32::from_le_bytes(take(data, pos, 4)?.try_into().unwrap());
+    take(data, pos, len as usize)
+}
+
+impl RawComments {
+    //`None` if the block is cut off or has anything after the last comment.
+    fn parse(data: &[u8]) -> Option<Self> {
+        let mut pos = 0;
+        let vendor = take_string(data, &mut pos)?.to_vec();
+        let count = u32::from_le_bytes(take(data, &mut pos, 4)?.try_into().unwrap());
+        let entries = (0..count)
+            .map(|_| take_string(data, &mut pos).map(<[u8]>::to_vec))
+            .collect::<Option<Vec<_>>>()?;
+        (pos == data.len()).then_some(Self { vendor, entries })
+    }
+
+    fn set(&mut self, field: Field, value: &str) {
+        let keys = field.vorbis();
+        //Field names are case insensitive, entries without a name never match.
+        let matches = |entry: &Vec<u8>| match entry.iter().position(|b| *b == b'=') {
+            Some(i) => keys
+                .iter()
+                .any(|k| k.as_bytes().eq_ignore_ascii_case(&entry[..i])),
+            None => false,
+        };
+        let position = self.entries.iter().position(matches);
+        self.entries.retain(|entry| !matches(entry));
+
+        //Keep the tag where it was.
+        let index = position.unwrap_or(self.entries.len());
+        for (i, value) in values(field, value).into_iter().enumerate() {
+            let entry = format!("{}={value}", keys[0]).into_bytes();
+            self.entries.insert(index + i, entry);
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend((self.vendor.len() as u32).to_le_bytes());
+        data.extend(&self.vendor);
+        data.extend((self.entries.len() as u32).to_le_bytes());
+        for entry in &self.entries {
+            data.extend((entry.len() as u32).to_le_bytes());
+            data.extend(entry);
+        }
+        data
+    }
+}
+
+//(Block type, data) for every metadata block.
+fn read_blocks<R: Read>(reader: &mut R) -> Result<Vec<(u8, Vec<u8>)>, FlacError> {
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != b"fLaC" {
+        return Err(FlacError::NotFlac);
+    }
+
+    let mut blocks = Vec::new();
+    loop {
+        let mut header = [0; 4];
+        reader.read_exact(&mut header)?;
+        let len = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+        let mut data = vec![0; len];
+        reader.read_exact(&mut data)?;
+        blocks.push((header[0] & 0x7f, data));
+
+        if header[0] & 0x80 != 0 {
+            return Ok(blocks);
+        }
+    }
+}
+
+fn encode_blocks(blocks: &[(u8, Vec<u8>)], padding: usize) -> Result<Vec<u8>, TagError> {
+    let mut bytes = b"fLaC".to_vec();
+    let padding = (1, vec![0; padding]);
+    let blocks: Vec<_> = blocks.iter().chain([&padding]).collect();
+
+    for (i, (kind, data)) in blocks.iter().enumerate() {
+        if data.len() >= 1 << 24 {
+            return Err(TagError::TooLarge);
+        }
+        let last = if i + 1 == blocks.len() { 0x80 } else { 0 };
+        bytes.push(kind | last);
+        bytes.extend(&(data.len() as u32).to_be_bytes()[1..]);
+        bytes.extend(data);
+    }
+
+    Ok(bytes)
+}
+
+fn write_flac(path: &Path, changes: &[(Field, String)]) -> Result<(), TagError> {
+    let blocks = read_blocks(&mut BufReader::new(File::open(path)?))?;
+    if blocks.first().is_none_or(|(kind, _)| *kind != 0) {
+        return Err(FlacError::MissingStreamInfo.into());
+    }
+    let audio = 4 + blocks.iter().map(|(_, data)| 4 + data.len()).sum::<usize>();
+
+    //Writing a block that couldn't be read completely would lose tags.
+    let mut comments = match blocks.iter().find(|(kind, _)| *kind == 4) {
+        Some((_, data)) => RawComments::parse(data).ok_or(TagError::Unsupported)?,
+        None => RawComments::default(),
+    };
+    for (field, value) in changes {
+        comments.set(*field, value);
+    }
+
+    //Padding is added back at the end.
+    let mut blocks: Vec<_> = blocks.into_iter().filter(|(kind, _)| *kind != 1).collect();
+    let comments = comments.encode();
+    match blocks.iter_mut().find(|(kind, _)| *kind == 4) {
+        Some((_, data)) => *data = comments,
+        None => blocks.insert(1, (4, comments)),
+    }
+
+    //The padding block header takes 4 bytes.
+    let used = 4 + blocks.iter().map(|(_, data)| 4 + data.len()).sum::<usize>() + 4;
+    if used <= audio {
+        overwrite(path, &encode_blocks(&blocks, audio - used)?)?;
+    } else {
+        replace(path, &encode_blocks(&blocks, PADDING)?, audio as u64)?;
+    }
+
+    Ok(())
+}
+
+struct Frame {
+    id: [u8; 4],
+    flags: [u8; 2],
+    data: Vec<u8>,
+}
+
+//Frames are kept as they are, unlike `id3::read_tag`.
+//Returns the version, the size of the tag and the frames.
+//Every frame is written back, so anything that can't be read is an error instead of being dropped.
+fn read_frames<R: Read>(reader: &mut R) -> Result<(u8, u64, Vec<Frame>), TagError> {
+    let mut header = [0; 10];
+    if reader.read_exact(&mut header).is_err() || &header[..3] != b"ID3" {
+        //Files without a tag get version 2.3, it's the most widely supported.
+        return Ok((3, 0, Vec::new()));
+    }
+
+    let version = header[3];
+    if !(3..=4).contains(&version) {
+        return Err(TagError::Unsupported);
+    }
+    let flags = header[5];
+    let len = syncsafe(&header[6..10]) as usize;
+    let size = 10 + len as u64 + if flags & 0x10 != 0 { 10 } else { 0 };
+
+    //Don't allocate the size from the header up front, it isn't checked yet.
+    let mut body = Vec::new();
+    reader.by_ref().take(len as u64).read_to_end(&mut body)?;
+    if body.len() != len {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+
+    let tag_unsync = flags & 0x80 != 0;
+    if tag_unsync && version == 3 {
+        body = remove_unsync(&body);
+    }
+
+    let mut pos = 0;
+    if flags & 0x40 != 0 {
+        let bytes = body.get(..4).ok_or(TagError::Unsupported)?;
+        pos = match version {
+            3 => u32::from_be_bytes(bytes.try_into().unwrap()) as usize + 4,
+            _ => syncsafe(bytes) as usize,
+        };
+    }
+
+    let mut frames = Vec::new();
+    while let Some(header) = body.get(pos..pos + 10) {
+        //Padding.
+        if header[0] == 0 {
+            break;
+        }
+        //Some taggers write version 2.4 frame sizes without syncsafe integers.
+        if !header[..4]
+            .iter()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+        {
+            return Err(TagError::Unsupported);
+        }
+        let len = match version {
+            3 => u32::from_be_bytes(header[4..8].try_into().unwrap()) as usize,
+            _ => syncsafe(&header[4..8]) as usize,
+        };
+        let data = body
+            .get(pos + 10..pos + 10 + len)
+            .ok_or(TagError::Unsupported)?;
+
+        let mut flags = [header[8], header[9]];
+        //The tag won't be unsynchronised anymore, so each frame has to say it is.
+        if tag_unsync && version == 4 {
+            flags[1] |= 0x02;
+        }
+        frames.push(Frame {
+            id: header[..4].try_into().unwrap(),
+            flags,
+            data: data.to_vec(),
+        });
+        pos += 10 + len;
+    }
+
+    //Only padding can be left over.
+    if body[pos.min(body.len())..].iter().any(|b| *b != 0) {
+        return Err(TagError::Unsupported);
+    }
+
+    Ok((version, size, frames))
+}
+
+//Version 2.4 uses UTF-8 and can store multiple values, 2.3 uses UTF-16.
+fn text_frame(id: &str, values: &[String], version: u8) -> Frame {
+    let mut data = Vec::new();
+    if version == 4 {
+        data.push(3);
+        data.extend(values.join("\0").as_bytes());
+    } else {
+        data.extend([1, 0xff, 0xfe]);
+        for unit in values.join("; ").encode_utf16() {
+            data.extend(unit.to_le_bytes());
+        }
+    }
+
+    Frame {
+        id: id.as_bytes().try_into().unwrap(),
+        flags: [0; 2],
+        data,
+    }
+}
+
+fn to_syncsafe(n: usize) -> Result<[u8; 4], TagError> {
+    if n >= 1 << 28 {
+        return Err(TagError::TooLarge);
+    }
+    Ok([
+        (n >> 21) as u8 & 0x7f,
+        (n >> 14) as u8 & 0x7f,
+        (n >> 7) as u8 & 0x7f,
+        n as u8 & 0x7f,
+    ])
+}
+
+fn encode_tag(version: u8, frames: &[Frame], padding: usize) -> Result<Vec<u8>, TagError> {
+    let mut body = Vec::new();
+    for frame in frames {
+        body.extend(frame.id);
+        match version {
+            4 => body.extend(to_syncsafe(frame.data.len())?),
+            _ => body.extend((frame.data.len() as u32).to_be_bytes()),
+        }
+        body.extend(frame.flags);
+        body.extend(&frame.data);
+    }
+    body.resize(body.len() + padding, 0);
+
+    let mut tag = vec![b'I', b'D', b'3', version, 0, 0];
+    tag.extend(to_syncsafe(body.len())?);
+    tag.extend(body);
+    Ok(tag)
+}
+
+//A new tag hides the ID3v1 tag from `id3::read_song`, so it starts with the same values.
+fn v1_frames(path: &Path, version: u8) -> Result<Vec<Frame>, TagError> {
+    let mut song = Song {
+        title: String::new(),
+        album: String::new(),
+        artist: String::new(),
+        track_artist: String::new(),
+        track_number: 0,
+        ..Song::default()
+    };
+    if !id3::read_v1(&mut File::open(path)?, &mut song)? {
+        return Ok(Vec::new());
+    }
+
+    let fields = [
+        Field::Title,
+        Field::TrackArtist,
+        Field::Album,
+        Field::TrackNumber,
+        Field::Year,
+        Field::Genre,
+    ];
+    Ok(fields
+        .into_iter()
+        .filter_map(|field| {
+            let value = match field {
+                Field::TrackNumber if song.track_number == 0 => String::new(),
+                _ => field.value(&song),
+            };
+            let values = values(field, &value);
+            (!values.is_empty()).then(|| text_frame(field.id3(version)[0], &values, version))
+        })
+        .collect())
+}
+
+fn write_id3(path: &Path, changes: &[(Field, String)]) -> Result<(), TagError> {
+    let (version, size, mut frames) = read_frames(&mut BufReader::new(File::open(path)?))?;
+    if size == 0 {
+        frames = v1_frames(path, version)?;
+    }
+
+    for (field, value) in changes {
+        let ids = field.id3(version);
+        let position = frames
+            .iter()
+            .position(|frame| ids.iter().any(|id| id.as_bytes() == frame.id));
+        frames.retain(|frame| !ids.iter().any(|id| id.as_bytes() == frame.id));
+
+        let values = values(*field, value);
+        if !values.is_empty() {
+            let index = position.unwrap_or(frames.len());
+            frames.insert(index, text_frame(ids[0], &values, version));
+        }
+    }
+
+    let tag = encode_tag(version, &frames, 0)?;
+    if size != 0 && tag.len() as u64 <= size {
+        let padding = (size - tag.len() as u64) as usize;
+        overwrite(path, &encode_tag(version, &frames, padding)?)?;
+    } else {
+        replace(path, &encode_tag(version, &frames, PADDING)?, size)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::read_flac;
+
+    #[test]
+    fn flac() {
+        let dir = std::env::temp_dir().join("gonk_edit");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("01.flac");
+
+        let comments = RawComments {
+            vendor: b"test".to_vec(),
+            entries: vec![
+                b"title=Tilte".to_vec(),
+                b"GENRE=Jazz".to_vec(),
+                b"COMMENT=\xff\xfe".to_vec(),
+                b"broken".to_vec(),
+            ],
+        };
+        let blocks = [(0, vec![0; 34]), (4, comments.encode())];
+        let mut bytes = encode_blocks(&blocks, 64).unwrap();
+        bytes.extend(b"audio");
+        fs::write(&path, &bytes).unwrap();
+
+        //Fits in the padding.
+        write(&path, &[(Field::Title, "Title".to_string())]).unwrap();
+        let file = fs::read(&path).unwrap();
+        assert_eq!(file.len(), bytes.len());
+        assert!(file.ends_with(b"audio"));
+        let flac = read_flac(&mut io::Cursor::new(file), false).unwrap();
+        assert_eq!(flac.comments.get("TITLE"), Some("Title"));
+
+        //Needs a new file.
+        let genre = "Fusion; ".repeat(20);
+        write(&path, &[(Field::Genre, genre)]).unwrap();
+        let file = fs::read(&path).unwrap();
+        assert!(file.len() > bytes.len() + PADDING);
+        assert!(file.ends_with(b"audio"));
+        let flac = read_flac(&mut io::Cursor::new(file), false).unwrap();
+        assert_eq!(flac.comments.all("GENRE").count(), 20);
+        assert_eq!(flac.comments.tags[0].0, "TITLE");
+
+        //Fields that weren't edited are written back exactly.
+        let blocks = read_blocks(&mut io::Cursor::new(fs::read(&path).unwrap())).unwrap();
+        let comments = RawComments::parse(&blocks[1].1).unwrap();
+        assert_eq!(comments.vendor, b"test");
+        assert_eq!(comments.entries[0], b"TITLE=Title");
+        assert_eq!(
+            comments.entries[21..],
+            [&b"COMMENT=\xff\xfe"[..], b"broken"]
+        );
+
+        //The comment count is larger than the block.
+        let mut data = comments.encode();
+        data[8..12].copy_from_slice(&100u32.to_le_bytes());
+        let mut bytes = encode_blocks(&[(0, vec![0; 34]), (4, data)], 64).unwrap();
+        bytes.extend(b"audio");
+        fs::write(&path, &bytes).unwrap();
+        assert!(matches!(
+            write(&path, &[(Field::Title, "New".to_string())]),
+            Err(TagError::Unsupported)
+        ));
+        assert_eq!(fs::read(&path).unwrap(), bytes);
+
+        assert!(matches!(
+            write(&path, &[(Field::Year, "Soon".to_string())]),
+            Err(TagError::InvalidValue("Year"))
+        ));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn mp3() {
+        let dir = std::env::temp_dir().join("gonk_edit_mp3");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("01.mp3");
+
+        //No tag.
+        fs::write(&path, b"audio").unwrap();
+        write(&path, &[(Field::Artist, "Foo".to_string())]).unwrap();
+        let tag = id3::read_tag(&mut File::open(&path).unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(tag.version, 3);
+        assert_eq!(tag.text("TPE2").unwrap(), "Foo");
+        assert!(fs::read(&path).unwrap().ends_with(b"audio"));
+
+        //Version 2.4 with padding.
+        let frames = [text_frame("TIT2", &["Old".to_string()], 4)];
+        let mut bytes = encode_tag(4, &frames, 100).unwrap();
+        bytes.extend(b"audio");
+        fs::write(&path, &bytes).unwrap();
+        write(
+            &path,
+            &[
+                (Field::Title, "New".to_string()),
+                (Field::Genre, "Jazz; Fusion".to_string()),
+            ],
+        )
+        .unwrap();
+        let file = fs::read(&path).unwrap();
+        assert_eq!(file.len(), bytes.len());
+        let tag = id3::read_tag(&mut io::Cursor::new(file)).unwrap().unwrap();
+        assert_eq!(tag.text("TIT2").unwrap(), "New");
+        let tcon = &tag.frames.iter().find(|f| f.id == "TCON").unwrap().data;
+        assert_eq!(id3::text(tcon), ["Jazz", "Fusion"]);
+
+        //A frame that runs past the end of the tag.
+        let mut bytes = encode_tag(3, &[text_frame("TIT2", &["Old".to_string()], 3)], 0).unwrap();
+        bytes[17] = 0xff;
+        bytes.extend(b"audio");
+        fs::write(&path, &bytes).unwrap();
+        assert!(matches!(
+            write(&path, &[(Field::Title, "New".to_string())]),
+            Err(TagError::Unsupported)
+        ));
+        assert_eq!(fs::read(&path).unwrap(), bytes);
+
+        //Only an ID3v1 tag.
+        let mut bytes = b"audio".to_vec();
+        let mut v1 = [0; 128];
+        v1[..3].copy_from_slice(b"TAG");
+        v1[3..8].copy_from_slice(b"Title");
+        v1[63..68].copy_from_slice(b"Album");
+        v1[126] = 7;
+        v1[127] = 0xff;
+        bytes.extend(v1);
+        fs::write(&path, &bytes).unwrap();
+        write(&path, &[(Field::Artist, "Foo".to_string())]).unwrap();
+        let tag = id3::read_tag(&mut File::open(&path).unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(tag.text("TIT2").unwrap(), "Title");
+        assert_eq!(tag.text("TALB").unwrap(), "Album");
+        assert_eq!(tag.text("TRCK").unwrap(), "7");
+        assert_eq!(tag.text("TPE2").unwrap(), "Foo");
+        assert!(tag.text("TCON").is_none());
+        assert!(fs::read(&path).unwrap().ends_with(&v1));
+
+        //Version 2.2 can't be written.
+        fs::write(&path, b"ID3\x02\x00\x00\x00\x00\x00\x00audio").unwrap();
+        assert!(matches!(
+            write(&path, &[(Field::Title, "Title".to_string())]),
+            Err(TagError::Unsupported)
+        ));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
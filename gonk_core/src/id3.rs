@@ -0,0 +1,785 @@
+//! Native MP3 metadata reader
+//!
+//! Reads ID3v2.2, ID3v2.3 and ID3v2.4 tags, falling back to ID3v1 at the end of the file.
+//! The duration comes from the Xing/Info or VBRI header in the first frame,
+//! otherwise it's estimated from the bitrate.
+//!
+//! https://id3.org/id3v2.4.0-structure
+use crate::{
+    db::{bitrate, parse_year, push_genre, UNKNOWN_ARTIST},
+    parse_gain, parse_number, Picture, Song,
+};
+use std::{
+    error::Error,
+    fmt,
+    fs::File,
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    path::Path,
+    time::Duration,
+};
+
+//How far to look for the first frame after the tag.
+const MAX_SEARCH: usize = 64 * 1024;
+
+#[derive(Debug)]
+pub enum Mp3Error {
+    Io(io::Error),
+    ///Couldn't find a valid MPEG layer 3 frame.
+    NoAudio,
+}
+
+impl fmt::Display for Mp3Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mp3Error::Io(err) => write!(f, "{err}"),
+            Mp3Error::NoAudio => write!(f, "No MP3 frames found."),
+        }
+    }
+}
+
+impl Error for Mp3Error {}
+
+impl From<io::Error> for Mp3Error {
+    fn from(err: io::Error) -> Self {
+        Mp3Error::Io(err)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    ///Four character ID, version 2.2 IDs are converted.
+    pub id: String,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tag {
+    ///Major version, 2, 3 or 4.
+    pub version: u8,
+    ///Total size including the header.
+    pub size: u64,
+    pub frames: Vec<Frame>,
+    ///The file or a frame ended early, anything after it is missing.
+    pub truncated: bool,
+}
+
+impl Tag {
+    ///The text of the first frame with this ID.
+    pub fn text(&self, id: &str) -> Option<String> {
+        let frame = self.frames.iter().find(|frame| frame.id == id)?;
+        text(&frame.data).into_iter().next()
+    }
+
+    ///Every attached picture.
+    pub fn pictures(&self) -> Vec<Picture> {
+        self.frames.iter().filter_map(picture).collect()
+    }
+
+    ///Copy the tags gonk uses into a song.
+    pub fn apply(&self, song: &mut Song) {
+        let mut track_artist = None;
+        let mut album_artist = None;
+
+        for frame in &self.frames {
+            let values = text(&frame.data);
+            let Some(value) = values.first() else {
+                continue;
+            };
+
+            match frame.id.as_str() {
+                "TIT2" => song.title = value.clone(),
+                "TALB" => song.album = value.clone(),
+                "TPE1" => track_artist = Some(value.clone()),
+                "TPE2" => album_artist = Some(value.clone()),
+                "TRCK" => song.track_number = parse_number(value).unwrap_or(1),
+                "TPOS" => song.disc_number = parse_number(value).unwrap_or(1),
+                "TCOM" => song.composer = value.clone(),
+                "TCON" => {
+                    for value in &values {
+                        push_genre(&mut song.genre, &genre(value));
+                    }
+                }
+                "TDRC" | "TYER" if song.year == 0 => {
+                    song.year = parse_year(value).unwrap_or_default()
+                }
+                //Description followed by the value.
+                "TXXX" if value.eq_ignore_ascii_case("replaygain_track_gain") => {
+                    if let Some(gain) = values.get(1).and_then(|v| parse_gain(v)) {
+                        song.gain = gain;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        song.artist = album_artist
+            .or(track_artist.clone())
+            .unwrap_or_else(|| UNKNOWN_ARTIST.to_string());
+        song.track_artist = track_artist.unwrap_or_else(|| song.artist.clone());
+    }
+}
+
+pub(crate) fn syncsafe(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0, |n, b| (n << 7) | (*b as u32 & 0x7f))
+}
+
+//0xFF 0x00 is used to avoid false MPEG frame syncs.
+pub(crate) fn remove_unsync(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    for (i, b) in data.iter().enumerate() {
+        if *b == 0 && i > 0 && data[i - 1] == 0xff {
+            continue;
+        }
+        out.push(*b);
+    }
+    out
+}
+
+fn v22_id(id: &[u8]) -> Option<&'static str> {
+    Some(match id {
+        b"TT2" => "TIT2",
+        b"TAL" => "TALB",
+        b"TP1" => "TPE1",
+        b"TP2" => "TPE2",
+        b"TRK" => "TRCK",
+        b"TPA" => "TPOS",
+        b"TCO" => "TCON",
+        b"TCM" => "TCOM",
+        b"TYE" => "TYER",
+        b"TXX" => "TXXX",
+        b"PIC" => "PIC",
+        _ => return None,
+    })
+}
+
+///Read the ID3v2 tag at the start of the file.
+///
+///The reader is left at the start of the audio, frames that are compressed,
+///encrypted or cut off are skipped.
+pub fn read_tag<R: Read + Seek>(reader: &mut R) -> io::Result<Option<Tag>> {
+    reader.seek(SeekFrom::Start(0))?;
+    let mut header = [0; 10];
+    if reader.read_exact(&mut header).is_err() || &header[..3] != b"ID3" {
+        reader.seek(SeekFrom::Start(0))?;
+        return Ok(None);
+    }
+
+    let version = header[3];
+    let flags = header[5];
+    let len = syncsafe(&header[6..10]) as usize;
+    //A footer is a copy of the header at the end.
+    let size = 10 + len as u64 + if flags & 0x10 != 0 { 10 } else { 0 };
+
+    //Keep what's there if the tag is cut off. The size isn't trusted
+    //for the allocation, a broken header can claim up to 256 MiB.
+    let mut body = Vec::new();
+    reader.by_ref().take(len as u64).read_to_end(&mut body)?;
+    reader.seek(SeekFrom::Start(size))?;
+    let mut truncated = body.len() < len;
+
+    if !(2..=4).contains(&version) {
+        return Ok(Some(Tag {
+            version,
+            size,
+            frames: Vec::new(),
+            truncated,
+        }));
+    }
+
+    let tag_unsync = flags & 0x80 != 0;
+    if tag_unsync && version < 4 {
+        body = remove_unsync(&body);
+    }
+
+    let mut pos = 0;
+    if flags & 0x40 != 0 && version >= 3 {
+        //Extended header, version 3 doesn't include the size field.
+        let Some(bytes) = body.get(..4) else {
+            return Ok(None);
+        };
+        pos = match version {
+            3 => u32::from_be_bytes(bytes.try_into().unwrap()) as usize + 4,
+            _ => syncsafe(bytes) as usize,
+        };
+    }
+
+    let header_len = if version == 2 { 6 } else { 10 };
+    let mut frames = Vec::new();
+
+    while let Some(header) = body.get(pos..pos + header_len) {
+        //Padding.
+        if header[0] == 0 {
+            break;
+        }
+
+        let (id, len, flags) = match version {
+            2 => {
+                let len = u32::from_be_bytes([0, header[3], header[4], header[5]]);
+                let id = v22_id(&header[..3]).unwrap_or_default().to_string();
+                (id, len as usize, 0)
+            }
+            3 => (
+                String::from_utf8_lossy(&header[..4]).to_string(),
+                u32::from_be_bytes(header[4..8].try_into().unwrap()) as usize,
+                header[9],
+            ),
+            _ => (
+                String::from_utf8_lossy(&header[..4]).to_string(),
+                syncsafe(&header[4..8]) as usize,
+                header[9],
+            ),
+        };
+        pos += header_len;
+
+        let Some(data) = body.get(pos..pos + len) else {
+            truncated = true;
+            break;
+        };
+        pos += len;
+
+        if id.is_empty() {
+            continue;
+        }
+
+        let data = match version {
+            3 => {
+                //Compressed or encrypted.
+                if flags & 0xc0 != 0 {
+                    continue;
+                }
+                //Grouping identity.
+                if flags & 0x20 != 0 {
+                    data.get(1..).unwrap_or_default().to_vec()
+                } else {
+                    data.to_vec()
+                }
+            }
+            4 => {
+                if flags & 0x0c != 0 {
+                    continue;
+                }
+                let mut data = data;
+                if flags & 0x40 != 0 {
+                    data = data.get(1..).unwrap_or_default();
+                }
+                //Data length indicator.
+                if flags & 0x01 != 0 {
+                    data = data.get(4..).unwrap_or_default();
+                }
+                if flags & 0x02 != 0 || tag_unsync {
+                    remove_unsync(data)
+                } else {
+                    data.to_vec()
+                }
+            }
+            _ => data.to_vec(),
+        };
+
+        frames.push(Frame { id, data });
+    }
+
+    Ok(Some(Tag {
+        version,
+        size,
+        frames,
+        truncated,
+    }))
+}
+
+fn utf16(units: &[[u8; 2]], big_endian: bool) -> String {
+    let units: Vec<u16> = units
+        .iter()
+        .map(|b| {
+            if big_endian {
+                u16::from_be_bytes(*b)
+            } else {
+                u16::from_le_bytes(*b)
+            }
+        })
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+///Decode a text frame, version 2.4 can store multiple values separated by null.
+pub fn text(data: &[u8]) -> Vec<String> {
+    let Some((&encoding, data)) = data.split_first() else {
+        return Vec::new();
+    };
+
+    let values: Vec<String> = match encoding {
+        //UTF-16 with a byte order mark, or big endian without one.
+        1 | 2 => {
+            let units: Vec<[u8; 2]> = data.chunks_exact(2).map(|b| [b[0], b[1]]).collect();
+            units
+                .split(|unit| *unit == [0, 0])
+                .map(|value| match value {
+                    [[0xff, 0xfe], rest @ ..] => utf16(rest, false),
+                    [[0xfe, 0xff], rest @ ..] => utf16(rest, true),
+                    _ => utf16(value, encoding == 2),
+                })
+                .collect()
+        }
+        3 => data
+            .split(|b| *b == 0)
+            .map(|s| String::from_utf8_lossy(s).to_string())
+            .collect(),
+        //ISO-8859-1
+        _ => data
+            .split(|b| *b == 0)
+            .map(|s| s.iter().map(|b| *b as char).collect())
+            .collect(),
+    };
+
+    //Drop the empty value after a trailing null.
+    let mut values = values;
+    while values.len() > 1 && values.last().is_some_and(|v| v.is_empty()) {
+        values.pop();
+    }
+    values
+}
+
+///Decode an APIC frame, or PIC in version 2.2.
+pub fn picture(frame: &Frame) -> Option<Picture> {
+    let (&encoding, data) = frame.data.split_first()?;
+    let (mime, data) = match frame.id.as_str() {
+        "APIC" => {
+            let end = data.iter().position(|b| *b == 0)?;
+            (
+                String::from_utf8_lossy(&data[..end]).to_string(),
+                &data[end + 1..],
+            )
+        }
+        //A three letter image format instead of a mime type.
+        "PIC" => {
+            let (format, data) = data.split_at_checked(3)?;
+            let mime = if format.eq_ignore_ascii_case(b"PNG") {
+                "image/png"
+            } else {
+                "image/jpeg"
+            };
+            (mime.to_string(), data)
+        }
+        _ => return None,
+    };
+
+    let (&picture_type, data) = data.split_first()?;
+
+    //The description is terminated with a null in the frame's encoding.
+    let (description, data) = match encoding {
+        1 | 2 => {
+            let end = data.chunks_exact(2).position(|c| c == [0, 0])? * 2;
+            (&data[..end], &data[end + 2..])
+        }
+        _ => {
+            let end = data.iter().position(|b| *b == 0)?;
+            (&data[..end], &data[end + 1..])
+        }
+    };
+    let description = text(&[&[encoding], description].concat())
+        .into_iter()
+        .next()
+        .unwrap_or_default();
+
+    Some(Picture {
+        picture_type: picture_type as u32,
+        mime,
+        description,
+        data: data.to_vec(),
+        ..Default::default()
+    })
+}
+
+const GENRES: [&str; 80] = [
+    "Blues",
+    "Classic Rock",
+    "Country",
+    "Dance",
+    "Disco",
+    "Funk",
+    "Grunge",
+    "Hip-Hop",
+    "Jazz",
+    "Metal",
+    "New Age",
+    "Oldies",
+    "Other",
+    "Pop",
+    "R&B",
+    "Rap",
+    "Reggae",
+    "Rock",
+    "Techno",
+    "Industrial",
+    "Alternative",
+    "Ska",
+    "Death Metal",
+    "Pranks",
+    "Soundtrack",
+    "Euro-Techno",
+    "Ambient",
+    "Trip-Hop",
+    "Vocal",
+    "Jazz+Funk",
+    "Fusion",
+    "Trance",
+    "Classical",
+    "Instrumental",
+    "Acid",
+    "House",
+    "Game",
+    "Sound Clip",
+    "Gospel",
+    "Noise",
+    "Alternative Rock",
+    "Bass",
+    "Soul",
+    "Punk",
+    "Space",
+    "Meditative",
+    "Instrumental Pop",
+    "Instrumental Rock",
+    "Ethnic",
+    "Gothic",
+    "Darkwave",
+    "Techno-Industrial",
+    "Electronic",
+    "Pop-Folk",
+    "Eurodance",
+    "Dream",
+    "Southern Rock",
+    "Comedy",
+    "Cult",
+    "Gangsta",
+    "Top 40",
+    "Christian Rap",
+    "Pop/Funk",
+    "Jungle",
+    "Native American",
+    "Cabaret",
+    "New Wave",
+    "Psychedelic",
+    "Rave",
+    "Showtunes",
+    "Trailer",
+    "Lo-Fi",
+    "Tribal",
+    "Acid Punk",
+    "Acid Jazz",
+    "Polka",
+    "Retro",
+    "Musical",
+    "Rock & Roll",
+    "Hard Rock",
+];
+
+///Genres can be ID3v1 numbers like `17` or `(17)`, optionally followed by a name.
+fn genre(value: &str) -> String {
+    let number = match value.strip_prefix('(').and_then(|v| v.split_once(')')) {
+        Some((_, name)) if !name.is_empty() => return name.to_string(),
+        Some((number, _)) => number,
+        None => value,
+    };
+    match number.parse::<usize>().ok().and_then(|i| GENRES.get(i)) {
+        Some(genre) => genre.to_string(),
+        None => value.to_string(),
+    }
+}
+
+///Read the ID3v1 tag in the last 128 bytes of the file.
+pub fn read_v1<R: Read + Seek>(reader: &mut R, song: &mut Song) -> io::Result<bool> {
+    let len = reader.seek(SeekFrom::End(0))?;
+    if len < 128 {
+        return Ok(false);
+    }
+    let mut tag = [0; 128];
+    reader.seek(SeekFrom::End(-128))?;
+    reader.read_exact(&mut tag)?;
+    if &tag[..3] != b"TAG" {
+        return Ok(false);
+    }
+
+    let field = |range: std::ops::Range<usize>| -> Option<String> {
+        let bytes = &tag[range];
+        let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+        let s: String = bytes[..end].iter().map(|b| *b as char).collect();
+        let s = s.trim().to_string();
+        (!s.is_empty()).then_some(s)
+    };
+
+    if let Some(title) = field(3..33) {
+        song.title = title;
+    }
+    if let Some(artist) = field(33..63) {
+        song.track_artist = artist.clone();
+        song.artist = artist;
+    }
+    if let Some(album) = field(63..93) {
+        song.album = album;
+    }
+    song.year = field(93..97)
+        .and_then(|year| parse_year(&year))
+        .unwrap_or_default();
+    //Version 1.1 stores the track number at the end of the comment.
+    if tag[125] == 0 && tag[126] != 0 {
+        song.track_number = tag[126] as u16;
+    }
+    if let Some(genre) = GENRES.get(tag[127] as usize) {
+        song.genre = genre.to_string();
+    }
+
+    Ok(true)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FrameHeader {
+    mpeg1: bool,
+    bitrate: u32,
+    sample_rate: u32,
+    channels: u8,
+    len: usize,
+}
+
+impl FrameHeader {
+    fn samples(&self) -> u32 {
+        if self.mpeg1 {
+            1152
+        } else {
+            576
+        }
+    }
+}
+
+//Only layer 3 is supported.
+fn frame_header(bytes: &[u8]) -> Option<FrameHeader> {
+    const MPEG1: [u32; 15] = [
+        0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+    ];
+    const MPEG2: [u32; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+
+    let [0xff, b1, b2, b3, ..] = *bytes else {
+        return None;
+    };
+    if b1 & 0xe0 != 0xe0 || (b1 >> 1) & 0x3 != 1 {
+        return None;
+    }
+
+    let version = (b1 >> 3) & 0x3;
+    let mpeg1 = version == 3;
+    let bitrate = match version {
+        3 => MPEG1.get((b2 >> 4) as usize)?,
+        0 | 2 => MPEG2.get((b2 >> 4) as usize)?,
+        _ => return None,
+    } * 1000;
+    let sample_rate = match ((b2 >> 2) & 0x3, version) {
+        (3, _) => return None,
+        (i, 3) => [44100, 48000, 32000][i as usize],
+        (i, 2) => [22050, 24000, 16000][i as usize],
+        (i, _) => [11025, 12000, 8000][i as usize],
+    };
+    //Free format isn't supported.
+    if bitrate == 0 {
+        return None;
+    }
+
+    let padding = ((b2 >> 1) & 0x1) as usize;
+    let factor = if mpeg1 { 144 } else { 72 };
+    Some(FrameHeader {
+        mpeg1,
+        bitrate,
+        sample_rate,
+        channels: if b3 >> 6 == 3 { 1 } else { 2 },
+        len: (factor * bitrate / sample_rate) as usize + padding,
+    })
+}
+
+//Number of frames from a Xing/Info or VBRI header.
+fn vbr_frames(frame: &[u8], header: &FrameHeader) -> Option<u32> {
+    let side_info = match (header.mpeg1, header.channels) {
+        (true, 1) => 17,
+        (true, _) => 32,
+        (false, 1) => 9,
+        (false, _) => 17,
+    };
+    let u32_at = |i: usize| Some(u32::from_be_bytes(frame.get(i..i + 4)?.try_into().ok()?));
+
+    let xing = 4 + side_info;
+    if let Some(b"Xing" | b"Info") = frame.get(xing..xing + 4) {
+        let flags = u32_at(xing + 4)?;
+        return if flags & 1 != 0 {
+            u32_at(xing + 8)
+        } else {
+            None
+        };
+    }
+
+    let vbri = 4 + 32;
+    if frame.get(vbri..vbri + 4) == Some(b"VBRI") {
+        return u32_at(vbri + 14);
+    }
+
+    None
+}
+
+///Read a song, along with a description of any damage to its tags.
+pub fn read_song(path: &Path) -> Result<(Song, Option<&'static str>), Mp3Error> {
+    let file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut reader = BufReader::new(file);
+
+    let mut song = Song::default();
+    song.path = path.to_string_lossy().to_string();
+    song.codec = String::from("MP3");
+
+    let tag = read_tag(&mut reader)?;
+    let start = reader.stream_position()?;
+
+    let mut buffer = Vec::with_capacity(MAX_SEARCH);
+    reader
+        .by_ref()
+        .take(MAX_SEARCH as u64)
+        .read_to_end(&mut buffer)?;
+
+    //Make sure the next frame also lines up to avoid false syncs.
+    let (offset, header) = (0..buffer.len())
+        .filter_map(|i| Some((i, frame_header(&buffer[i..])?)))
+        .find(|(i, header)| match buffer.get(i + header.len..) {
+            Some(next) if next.len() >= 4 => frame_header(next).is_some(),
+            _ => true,
+        })
+        .ok_or(Mp3Error::NoAudio)?;
+
+    song.sample_rate = header.sample_rate;
+    song.channels = header.channels;
+
+    let audio = size.saturating_sub(start + offset as u64);
+    match vbr_frames(&buffer[offset..], &header) {
+        Some(frames) => {
+            song.duration = Duration::from_secs_f64(
+                frames as f64 * header.samples() as f64 / header.sample_rate as f64,
+            );
+            song.bitrate = bitrate(audio, song.duration);
+        }
+        None => {
+            song.duration = Duration::from_secs_f64(audio as f64 * 8.0 / header.bitrate as f64);
+            song.bitrate = header.bitrate / 1000;
+        }
+    }
+
+    let damage = match tag {
+        Some(tag) => {
+            tag.apply(&mut song);
+            tag.truncated.then_some("ID3 tag is cut off.")
+        }
+        None => {
+            read_v1(&mut reader, &mut song)?;
+            None
+        }
+    };
+
+    Ok((song, damage))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn frame(id: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut bytes = id.to_vec();
+        bytes.extend((data.len() as u32).to_be_bytes());
+        bytes.extend([0, 0]);
+        bytes.extend(data);
+        bytes
+    }
+
+    fn tag(frames: &[Vec<u8>]) -> Vec<u8> {
+        let body: Vec<u8> = frames.concat();
+        let len = body.len() as u32 + 16;
+        let mut bytes = b"ID3\x03\x00\x00".to_vec();
+        bytes.extend([
+            (len >> 21) as u8 & 0x7f,
+            (len >> 14) as u8 & 0x7f,
+            (len >> 7) as u8 & 0x7f,
+            len as u8 & 0x7f,
+        ]);
+        bytes.extend(body);
+        bytes.extend([0; 16]);
+        bytes
+    }
+
+    #[test]
+    fn frames() {
+        let mut utf16 = vec![1, 0xff, 0xfe];
+        for unit in "Tïtle".encode_utf16() {
+            utf16.extend(unit.to_le_bytes());
+        }
+
+        let bytes = tag(&[
+            frame(b"TIT2", &utf16),
+            frame(b"TPE1", b"\x00Track Artist"),
+            frame(b"TPE2", b"\x03Album Artist\x00"),
+            frame(b"TRCK", b"\x004/12"),
+            frame(b"TCON", b"\x00(8)"),
+            frame(b"TYER", b"\x001999"),
+            frame(b"TXXX", b"\x00REPLAYGAIN_TRACK_GAIN\x00-6.02 dB"),
+        ]);
+
+        let mut cursor = Cursor::new(&bytes);
+        let tag = read_tag(&mut cursor).unwrap().unwrap();
+        assert_eq!(tag.version, 3);
+        assert_eq!(cursor.position(), bytes.len() as u64);
+
+        let mut song = Song::default();
+        tag.apply(&mut song);
+        assert_eq!(song.title, "Tïtle");
+        assert_eq!(song.artist, "Album Artist");
+        assert_eq!(song.track_artist, "Track Artist");
+        assert_eq!(song.track_number, 4);
+        assert_eq!(song.genre, "Jazz");
+        assert_eq!(song.year, 1999);
+        assert!((song.gain - 0.5).abs() < 0.01);
+
+        assert_eq!(text(b"\x03One\x00Two"), ["One", "Two"]);
+        assert_eq!(genre("(17)"), "Rock");
+        assert_eq!(genre("(17)Indie Rock"), "Indie Rock");
+        assert_eq!(genre("Shoegaze"), "Shoegaze");
+
+        let apic = Frame {
+            id: String::from("APIC"),
+            data: b"\x00image/png\x00\x03Cover\x00\x89PNG".to_vec(),
+        };
+        let picture = picture(&apic).unwrap();
+        assert_eq!(picture.mime, "image/png");
+        assert_eq!(picture.picture_type, 3);
+        assert_eq!(picture.description, "Cover");
+        assert_eq!(picture.data, b"\x89PNG");
+    }
+
+    #[test]
+    fn mpeg() {
+        //MPEG1 layer 3, 128 kbps, 44100 Hz, joint stereo.
+        let header = frame_header(&[0xff, 0xfb, 0x90, 0x44]).unwrap();
+        assert!(header.mpeg1);
+        assert_eq!(header.bitrate, 128_000);
+        assert_eq!(header.sample_rate, 44100);
+        assert_eq!(header.len, 417);
+
+        let mut first = vec![0; header.len];
+        first[..4].copy_from_slice(&[0xff, 0xfb, 0x90, 0x44]);
+        first[36..40].copy_from_slice(b"Xing");
+        first[40..44].copy_from_slice(&1u32.to_be_bytes());
+        first[44..48].copy_from_slice(&1000u32.to_be_bytes());
+        assert_eq!(vbr_frames(&first, &header), Some(1000));
+
+        let mut v1 = vec![0; 128];
+        v1[..3].copy_from_slice(b"TAG");
+        v1[3..8].copy_from_slice(b"Title");
+        v1[126] = 7;
+        v1[127] = 17;
+        let mut song = Song::default();
+        assert!(read_v1(&mut Cursor::new(v1), &mut song).unwrap());
+        assert_eq!(song.title, "Title");
+        assert_eq!(song.track_number, 7);
+        assert_eq!(song.genre, "Rock");
+    }
+}
@@ -15,6 +15,7 @@ use std::{
     mem::MaybeUninit,
     path::{Path, PathBuf},
     sync::Once,
+    time::Duration,
 };
 
 pub use crate::{
@@ -24,14 +25,24 @@ pub use crate::{
 pub use flac_decoder::*;
 pub use index::*;
 
+pub mod art;
+pub mod bookmarks;
 pub mod db;
+pub mod duplicates;
+pub mod edit;
 pub mod flac_decoder;
+pub mod id3;
 pub mod index;
 pub mod log;
+pub mod ogg;
 pub mod playlist;
 pub mod settings;
+pub mod silence;
+pub mod stats;
 pub mod strsim;
 pub mod vdb;
+pub mod walk;
+pub mod watch;
 
 ///Escape potentially problematic strings.
 pub fn escape(input: &str) -> Cow<str> {
@@ -42,6 +53,16 @@ pub fn escape(input: &str) -> Cow<str> {
     }
 }
 
+///Format a duration as `m:ss`, or `h:mm:ss` if it's an hour or longer.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
 static mut GONK: MaybeUninit<PathBuf> = MaybeUninit::uninit();
 static mut SETTINGS: MaybeUninit<PathBuf> = MaybeUninit::uninit();
 static mut DATABASE: MaybeUninit<PathBuf> = MaybeUninit::uninit();
@@ -0,0 +1,308 @@
+//! Native Ogg Vorbis metadata reader
+//!
+//! Reads the identification and comment headers from the first two packets.
+//! The duration is the granule position of the last page divided by the sample rate.
+//!
+//! https://xiph.org/vorbis/doc/Vorbis_I_spec.html
+use crate::{
+    db::bitrate,
+    flac_decoder::{parse_picture, vorbis_comments},
+    Comments, Picture, Song,
+};
+use std::{
+    error::Error,
+    fmt,
+    fs::File,
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    path::Path,
+    time::Duration,
+};
+
+//The last page is always smaller than this.
+const MAX_PAGE: u64 = 64 * 1024;
+
+//Comment headers with large pictures can span many pages.
+const MAX_PACKET: usize = 16 * 1024 * 1024;
+
+#[derive(Debug)]
+pub enum OggError {
+    Io(io::Error),
+    ///The file doesn't start with `OggS`.
+    NotOgg,
+    ///The stream isn't Vorbis, e.g. Opus.
+    NotVorbis,
+    Truncated,
+}
+
+impl fmt::Display for OggError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OggError::Io(err) => write!(f, "{err}"),
+            OggError::NotOgg => write!(f, "File is not Ogg."),
+            OggError::NotVorbis => write!(f, "Stream is not Vorbis."),
+            OggError::Truncated => write!(f, "File is truncated."),
+        }
+    }
+}
+
+impl Error for OggError {}
+
+impl From<io::Error> for OggError {
+    fn from(err: io::Error) -> Self {
+        if err.kind() == io::ErrorKind::UnexpectedEof {
+            OggError::Truncated
+        } else {
+            OggError::Io(err)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Vorbis {
+    pub channels: u8,
+    pub sample_rate: u32,
+    ///Zero if the encoder didn't set it.
+    pub nominal_bitrate: u32,
+    pub comments: Comments,
+    ///Zero if the last page couldn't be found.
+    pub duration: Duration,
+}
+
+struct Page {
+    granule: u64,
+    serial: u32,
+    lacing: Vec<u8>,
+    data: Vec<u8>,
+}
+
+fn read_page<R: Read>(reader: &mut R) -> Result<Page, OggError> {
+    let mut header = [0; 27];
+    reader.read_exact(&mut header)?;
+    if &header[..4] != b"OggS" {
+        return Err(OggError::NotOgg);
+    }
+
+    let mut lacing = vec![0; header[26] as usize];
+    reader.read_exact(&mut lacing)?;
+    let mut data = vec![0; lacing.iter().map(|n| *n as usize).sum()];
+    reader.read_exact(&mut data)?;
+
+    Ok(Page {
+        granule: u64::from_le_bytes(header[6..14].try_into().unwrap()),
+        serial: u32::from_le_bytes(header[14..18].try_into().unwrap()),
+        lacing,
+        data,
+    })
+}
+
+//Read the first `count` packets of the first logical stream.
+fn read_packets<R: Read>(reader: &mut R, count: usize) -> Result<Vec<Vec<u8>>, OggError> {
+    let mut packets = Vec::new();
+    let mut packet = Vec::new();
+    let mut serial = None;
+
+    while packets.len() < count {
+        let page = read_page(reader)?;
+        if *serial.get_or_insert(page.serial) != page.serial {
+            continue;
+        }
+
+        let mut start = 0;
+        for len in page.lacing {
+            let end = start + len as usize;
+            packet.extend_from_slice(&page.data[start..end]);
+            start = end;
+
+            //A lacing value of 255 means the packet continues.
+            if len < 255 {
+                packets.push(std::mem::take(&mut packet));
+                if packets.len() == count {
+                    break;
+                }
+            }
+        }
+
+        if packet.len() > MAX_PACKET {
+            return Err(OggError::Truncated);
+        }
+    }
+
+    Ok(packets)
+}
+
+//Search backwards from the end of the file for the last page of the stream.
+fn last_granule<R: Read + Seek>(reader: &mut R) -> io::Result<Option<u64>> {
+    let len = reader.seek(SeekFrom::End(0))?;
+    let start = len.saturating_sub(MAX_PAGE);
+    reader.seek(SeekFrom::Start(start))?;
+    let mut buffer = Vec::new();
+    reader.read_to_end(&mut buffer)?;
+
+    let granule = (0..buffer.len().saturating_sub(27))
+        .rev()
+        .find(|&i| &buffer[i..i + 4] == b"OggS")
+        .map(|i| u64::from_le_bytes(buffer[i + 6..i + 14].try_into().unwrap()));
+
+    //-1 means no packets finish on this page.
+    Ok(granule.filter(|granule| *granule != u64::MAX))
+}
+
+pub fn read_vorbis<R: Read + Seek>(reader: &mut R) -> Result<Vorbis, OggError> {
+    reader.seek(SeekFrom::Start(0))?;
+    let packets = read_packets(reader, 2)?;
+
+    let id = &packets[0];
+    if id.len() < 30 || &id[..7] != b"\x01vorbis" {
+        return Err(OggError::NotVorbis);
+    }
+    let comment = &packets[1];
+    if comment.len() < 7 || &comment[..7] != b"\x03vorbis" {
+        return Err(OggError::NotVorbis);
+    }
+
+    let sample_rate = u32::from_le_bytes(id[12..16].try_into().unwrap());
+    let nominal_bitrate = i32::from_le_bytes(id[20..24].try_into().unwrap());
+
+    let duration = match last_granule(reader)? {
+        Some(granule) if sample_rate != 0 => {
+            Duration::from_secs_f64(granule as f64 / sample_rate as f64)
+        }
+        _ => Duration::ZERO,
+    };
+
+    Ok(Vorbis {
+        channels: id[11],
+        sample_rate,
+        nominal_bitrate: nominal_bitrate.max(0) as u32,
+        comments: vorbis_comments(&comment[7..]),
+        duration,
+    })
+}
+
+fn base64(input: &str) -> Option<Vec<u8>> {
+    let mut output = Vec::with_capacity(input.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for c in input
+        .bytes()
+        .filter(|c| *c != b'=' && !c.is_ascii_whitespace())
+    {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+        }
+    }
+
+    Some(output)
+}
+
+///Pictures are stored as base64 encoded FLAC picture blocks.
+pub fn pictures(comments: &Comments) -> Vec<Picture> {
+    comments
+        .all("METADATA_BLOCK_PICTURE")
+        .filter_map(|value| parse_picture(&base64(value)?))
+        .collect()
+}
+
+///Read a song, along with a description of any damage to its tags.
+pub fn read_song(path: &Path) -> Result<(Song, Option<&'static str>), OggError> {
+    let file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let vorbis = read_vorbis(&mut BufReader::new(file))?;
+
+    let mut song = Song::default();
+    song.path = path.to_string_lossy().to_string();
+    song.codec = String::from("Vorbis");
+    song.sample_rate = vorbis.sample_rate;
+    song.channels = vorbis.channels;
+    song.duration = vorbis.duration;
+    song.bitrate = match bitrate(size, song.duration) {
+        0 => vorbis.nominal_bitrate / 1000,
+        bitrate => bitrate,
+    };
+    vorbis.comments.apply(&mut song);
+
+    let damage = vorbis
+        .comments
+        .truncated
+        .then_some("Comment list is cut off.");
+    Ok((song, damage))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn page(bytes: &mut Vec<u8>, granule: u64, packets: &[&[u8]]) {
+        let mut lacing = Vec::new();
+        for packet in packets {
+            lacing.extend(std::iter::repeat_n(255, packet.len() / 255));
+            lacing.push((packet.len() % 255) as u8);
+        }
+        bytes.extend(b"OggS\x00\x02");
+        bytes.extend(granule.to_le_bytes());
+        bytes.extend(1u32.to_le_bytes());
+        bytes.extend([0; 8]);
+        bytes.push(lacing.len() as u8);
+        bytes.extend(lacing);
+        for packet in packets {
+            bytes.extend(*packet);
+        }
+    }
+
+    #[test]
+    fn vorbis() {
+        let mut id = b"\x01vorbis".to_vec();
+        id.extend(0u32.to_le_bytes());
+        id.push(2);
+        id.extend(48000u32.to_le_bytes());
+        id.extend(0i32.to_le_bytes());
+        id.extend(160_000i32.to_le_bytes());
+        id.extend(0i32.to_le_bytes());
+        id.extend([0xb8, 1]);
+
+        let mut comment = b"\x03vorbis".to_vec();
+        comment.extend(6u32.to_le_bytes());
+        comment.extend(b"vendor");
+        comment.extend(2u32.to_le_bytes());
+        let title = format!("TITLE={}", "a".repeat(300));
+        comment.extend((title.len() as u32).to_le_bytes());
+        comment.extend(title.as_bytes());
+        comment.extend(11u32.to_le_bytes());
+        comment.extend(b"ARTIST=Foo\x00");
+
+        let mut bytes = Vec::new();
+        page(&mut bytes, 0, &[&id]);
+        page(&mut bytes, 0, &[&comment]);
+        page(&mut bytes, 480_000, &[b"audio"]);
+
+        let vorbis = read_vorbis(&mut Cursor::new(bytes)).unwrap();
+        assert_eq!(vorbis.channels, 2);
+        assert_eq!(vorbis.sample_rate, 48000);
+        assert_eq!(vorbis.nominal_bitrate, 160_000);
+        assert_eq!(vorbis.duration, Duration::from_secs(10));
+        assert_eq!(vorbis.comments.get("TITLE").unwrap().len(), 300);
+        assert_eq!(vorbis.comments.get("ARTIST"), Some("Foo\0"));
+
+        assert_eq!(base64("Z29uaw==").unwrap(), b"gonk");
+        assert_eq!(base64("Z29u").unwrap(), b"gon");
+        assert!(base64("Z2*u").is_none());
+
+        assert!(matches!(
+            read_vorbis(&mut Cursor::new(b"ID3\x04".repeat(10))),
+            Err(OggError::NotOgg)
+        ));
+    }
+}
@@ -9,13 +9,39 @@ use std::{
     io::{BufWriter, Read, Seek, Write},
 };
 
+//Lists are joined with null since it can't be in a path or a pattern.
+const SEPARATOR: &str = "\0";
+
 #[derive(Debug)]
 pub struct Settings {
     pub volume: u8,
     pub index: u16,
     pub elapsed: f32,
     pub output_device: String,
-    pub music_folder: String,
+    ///Library roots, stored separated by null.
+    pub music_folders: Vec<String>,
+    pub skip_silence: bool,
+    pub cache_silence: bool,
+    ///Decibels
+    pub preamp: f32,
+    pub limiter: bool,
+    pub dither: bool,
+    ///-1.0 is fully left, 1.0 is fully right.
+    pub balance: f32,
+    pub mono: bool,
+    pub swap_channels: bool,
+    pub crossfeed: bool,
+    pub read_ahead: bool,
+    ///Load the next song in the queue ahead of time.
+    pub preload: bool,
+    ///Update the database when the music folder changes.
+    pub watch: bool,
+    ///Glob patterns excluded from scans, stored separated by null.
+    pub ignore: Vec<String>,
+    pub follow_symlinks: bool,
+    pub skip_hidden: bool,
+    ///Show the cover of the current album.
+    pub album_art: bool,
     pub queue: Vec<Song>,
     pub file: Option<File>,
 }
@@ -31,7 +57,41 @@ impl Serialize for Settings {
         buffer.push('\t');
         buffer.push_str(&escape(&self.output_device));
         buffer.push('\t');
-        buffer.push_str(&escape(&self.music_folder));
+        let folders: Vec<_> = self.music_folders.iter().map(|f| escape(f)).collect();
+        buffer.push_str(&folders.join(SEPARATOR));
+        buffer.push('\t');
+        buffer.push_str(&self.skip_silence.to_string());
+        buffer.push('\t');
+        buffer.push_str(&self.cache_silence.to_string());
+        buffer.push('\t');
+        buffer.push_str(&self.preamp.to_string());
+        buffer.push('\t');
+        buffer.push_str(&self.limiter.to_string());
+        buffer.push('\t');
+        buffer.push_str(&self.dither.to_string());
+        buffer.push('\t');
+        buffer.push_str(&self.balance.to_string());
+        buffer.push('\t');
+        buffer.push_str(&self.mono.to_string());
+        buffer.push('\t');
+        buffer.push_str(&self.swap_channels.to_string());
+        buffer.push('\t');
+        buffer.push_str(&self.crossfeed.to_string());
+        buffer.push('\t');
+        buffer.push_str(&self.read_ahead.to_string());
+        buffer.push('\t');
+        buffer.push_str(&self.preload.to_string());
+        buffer.push('\t');
+        buffer.push_str(&self.watch.to_string());
+        buffer.push('\t');
+        let ignore: Vec<_> = self.ignore.iter().map(|p| escape(p)).collect();
+        buffer.push_str(&ignore.join(SEPARATOR));
+        buffer.push('\t');
+        buffer.push_str(&self.follow_symlinks.to_string());
+        buffer.push('\t');
+        buffer.push_str(&self.skip_hidden.to_string());
+        buffer.push('\t');
+        buffer.push_str(&self.album_art.to_string());
         buffer.push('\n');
         buffer.push_str(&self.queue.serialize());
         buffer
@@ -44,10 +104,15 @@ impl Deserialize for Settings {
     fn deserialize(s: &str) -> Result<Self, Self::Error> {
         let (start, end) = s.split_once('\n').ok_or("Invalid settings")?;
         let split: Vec<&str> = start.split('\t').collect();
-        let music_folder = if split.len() == 4 {
-            String::new()
-        } else {
-            split[4].to_string()
+        let list = |s: Option<&&str>| -> Vec<String> {
+            match s {
+                Some(s) => s
+                    .split(SEPARATOR)
+                    .filter(|s| !s.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+                None => Vec::new(),
+            }
         };
 
         let queue = if end.is_empty() {
@@ -61,13 +126,35 @@ impl Deserialize for Settings {
             index: split[1].parse::<u16>()?,
             elapsed: split[2].parse::<f32>()?,
             output_device: split[3].to_string(),
-            music_folder,
+            music_folders: list(split.get(4)),
+            //Settings added after the music folder are optional.
+            skip_silence: parse_or(split.get(5), false),
+            cache_silence: parse_or(split.get(6), true),
+            preamp: parse_or(split.get(7), 0.0),
+            limiter: parse_or(split.get(8), true),
+            dither: parse_or(split.get(9), true),
+            balance: parse_or(split.get(10), 0.0),
+            mono: parse_or(split.get(11), false),
+            swap_channels: parse_or(split.get(12), false),
+            crossfeed: parse_or(split.get(13), false),
+            read_ahead: parse_or(split.get(14), false),
+            preload: parse_or(split.get(15), false),
+            watch: parse_or(split.get(16), false),
+            ignore: list(split.get(17)),
+            follow_symlinks: parse_or(split.get(18), false),
+            skip_hidden: parse_or(split.get(19), true),
+            album_art: parse_or(split.get(20), true),
             queue,
             file: None,
         })
     }
 }
 
+//Older settings files won't have every field.
+fn parse_or<T: std::str::FromStr>(s: Option<&&str>, default: T) -> T {
+    s.and_then(|s| s.parse().ok()).unwrap_or(default)
+}
+
 impl Default for Settings {
     fn default() -> Self {
         Self {
@@ -75,7 +162,23 @@ impl Default for Settings {
             index: Default::default(),
             elapsed: Default::default(),
             output_device: Default::default(),
-            music_folder: Default::default(),
+            music_folders: Default::default(),
+            skip_silence: false,
+            cache_silence: true,
+            preamp: 0.0,
+            limiter: true,
+            dither: true,
+            balance: 0.0,
+            mono: false,
+            swap_channels: false,
+            crossfeed: false,
+            read_ahead: false,
+            preload: false,
+            watch: false,
+            ignore: Vec::new(),
+            follow_symlinks: false,
+            skip_hidden: true,
+            album_art: true,
             queue: Default::default(),
             file: None,
         }
@@ -115,4 +218,16 @@ mod tests {
     fn settings() {
         Settings::new().unwrap();
     }
+
+    #[test]
+    fn lists() {
+        let settings = Settings {
+            music_folders: vec!["/music/a|b".to_string(), "/music/c".to_string()],
+            ignore: vec!["{live|demo}".to_string()],
+            ..Default::default()
+        };
+        let settings = Settings::deserialize(&settings.serialize()).unwrap();
+        assert_eq!(settings.music_folders, ["/music/a|b", "/music/c"]);
+        assert_eq!(settings.ignore, ["{live|demo}"]);
+    }
 }
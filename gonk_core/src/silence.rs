@@ -0,0 +1,103 @@
+//! Silent regions of songs
+//!
+//! The player detects silence while skipping it, the regions are
+//! cached here so they can be skipped straight away next time.
+//!
+//! Each line stores `start-end` pairs in seconds separated by spaces, then a path.
+//! The path goes last so it can contain tabs.
+use crate::{gonk_path, Deserialize, Serialize};
+use std::{collections::HashMap, fs, path::PathBuf};
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Silence {
+    path: PathBuf,
+    regions: HashMap<String, Vec<(f32, f32)>>,
+}
+
+impl Silence {
+    pub fn new() -> Self {
+        let path = gonk_path().join("silence.db");
+        let mut silence = match fs::read_to_string(&path) {
+            Ok(string) => Silence::deserialize(&string).unwrap_or_default(),
+            Err(_) => Silence::default(),
+        };
+        silence.path = path;
+        silence
+    }
+    pub fn get(&self, path: &str) -> Option<&[(f32, f32)]> {
+        self.regions.get(path).map(|regions| regions.as_slice())
+    }
+    pub fn set(&mut self, path: &str, regions: Vec<(f32, f32)>) {
+        self.regions.insert(path.to_string(), regions);
+    }
+    pub fn save(&self) -> std::io::Result<()> {
+        fs::write(&self.path, self.serialize())
+    }
+}
+
+impl Serialize for Silence {
+    fn serialize(&self) -> String {
+        let mut buffer = String::new();
+        for (path, regions) in &self.regions {
+            //A newline would split the path, so it just won't be cached.
+            if path.contains('\n') {
+                continue;
+            }
+            let regions: Vec<String> = regions
+                .iter()
+                .map(|(start, end)| format!("{start}-{end}"))
+                .collect();
+            buffer.push_str(&regions.join(" "));
+            buffer.push('\t');
+            buffer.push_str(path);
+            buffer.push('\n');
+        }
+        buffer
+    }
+}
+
+impl Deserialize for Silence {
+    type Error = Box<dyn std::error::Error>;
+
+    ///Lines that can't be read are skipped.
+    fn deserialize(s: &str) -> Result<Self, Self::Error> {
+        let mut regions = HashMap::new();
+        for line in s.lines() {
+            let Some((r, path)) = line.split_once('\t') else {
+                continue;
+            };
+            let r: Option<Vec<(f32, f32)>> = r
+                .split_whitespace()
+                .map(|region| {
+                    let (start, end) = region.split_once('-')?;
+                    Some((start.parse().ok()?, end.parse().ok()?))
+                })
+                .collect();
+            if let Some(r) = r {
+                regions.insert(path.to_string(), r);
+            }
+        }
+        Ok(Self {
+            path: PathBuf::new(),
+            regions,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn string() {
+        let mut silence = Silence::default();
+        silence.set("D:\\Music\\live.flac", vec![(0.0, 4.5), (300.25, 312.0)]);
+        silence.set("D:\\Music\\no silence.flac", Vec::new());
+        silence.set("D:\\Music\\tab\tlive.flac", vec![(1.0, 2.0)]);
+        let string = silence.serialize();
+        assert_eq!(Silence::deserialize(&string).unwrap(), silence);
+
+        let string = format!("{string}broken\n1-x\tD:\\Music\\other.flac\n");
+        assert_eq!(Silence::deserialize(&string).unwrap(), silence);
+    }
+}
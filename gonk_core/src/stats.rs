@@ -0,0 +1,160 @@
+//! Library statistics
+//!
+//! Counts are taken from the database entries so no files are read.
+use crate::db::{Entry, UNKNOWN_ALBUM, UNKNOWN_ARTIST, UNKNOWN_TITLE};
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+    time::Duration,
+};
+
+///The number of artists shown in the top artists.
+pub const TOP: usize = 10;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Stats {
+    pub artists: usize,
+    pub albums: usize,
+    pub songs: usize,
+    pub duration: Duration,
+    ///Bytes on disk.
+    pub size: u64,
+    ///Sorted by count, highest first.
+    pub formats: Vec<(String, usize)>,
+    ///Sorted by count, highest first. Zero if unknown.
+    pub sample_rates: Vec<(u32, usize)>,
+    ///Songs without a title, album or artist.
+    pub missing_tags: usize,
+    pub missing_gain: usize,
+    ///Album artists with the most songs.
+    pub top_artists: Vec<(String, usize)>,
+}
+
+//Ties are sorted by key so the order doesn't change between runs.
+fn sorted<K: Ord>(counts: HashMap<K, usize>) -> Vec<(K, usize)> {
+    let mut counts: Vec<(K, usize)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+}
+
+fn count<K: Hash + Eq>(counts: &mut HashMap<K, usize>, key: K) {
+    *counts.entry(key).or_default() += 1;
+}
+
+pub fn collect(entries: &[Entry]) -> Stats {
+    let mut artists = HashMap::new();
+    let mut albums = HashSet::new();
+    let mut formats = HashMap::new();
+    let mut sample_rates = HashMap::new();
+    let mut stats = Stats {
+        songs: entries.len(),
+        ..Stats::default()
+    };
+
+    for Entry { song, size, .. } in entries {
+        stats.duration += song.duration;
+        stats.size += size;
+
+        let codec = if song.codec.is_empty() {
+            "Unknown"
+        } else {
+            song.codec.as_str()
+        };
+        count(&mut formats, codec.to_string());
+        count(&mut sample_rates, song.sample_rate);
+        count(&mut artists, song.artist.as_str());
+        albums.insert((song.artist.as_str(), song.album.as_str()));
+
+        if song.title == UNKNOWN_TITLE
+            || song.album == UNKNOWN_ALBUM
+            || song.artist == UNKNOWN_ARTIST
+        {
+            stats.missing_tags += 1;
+        }
+        if song.gain == 0.0 {
+            stats.missing_gain += 1;
+        }
+    }
+
+    stats.artists = artists.len();
+    stats.albums = albums.len();
+    stats.formats = sorted(formats);
+    stats.sample_rates = sorted(sample_rates);
+    stats.top_artists = sorted(artists)
+        .into_iter()
+        .take(TOP)
+        .map(|(artist, count)| (artist.to_string(), count))
+        .collect();
+    stats
+}
+
+///Format bytes as `1.5 GB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Song;
+
+    fn entry(artist: &str, album: &str, codec: &str, gain: f32) -> Entry {
+        Entry {
+            song: Song {
+                artist: artist.to_string(),
+                album: album.to_string(),
+                title: "Title".to_string(),
+                codec: codec.to_string(),
+                sample_rate: 44100,
+                gain,
+                duration: Duration::from_secs(60),
+                ..Song::default()
+            },
+            size: 1024,
+            modified: 0,
+        }
+    }
+
+    #[test]
+    fn stats() {
+        let entries = [
+            entry("A", "One", "FLAC", 1.0),
+            entry("A", "Two", "FLAC", 0.0),
+            entry("B", "One", "MP3", 1.0),
+            entry(UNKNOWN_ARTIST, UNKNOWN_ALBUM, "", 0.0),
+        ];
+        let stats = collect(&entries);
+        assert_eq!(stats.artists, 3);
+        assert_eq!(stats.albums, 4);
+        assert_eq!(stats.songs, 4);
+        assert_eq!(stats.duration, Duration::from_secs(240));
+        assert_eq!(stats.size, 4096);
+        assert_eq!(
+            stats.formats,
+            [
+                ("FLAC".to_string(), 2),
+                ("MP3".to_string(), 1),
+                ("Unknown".to_string(), 1)
+            ]
+        );
+        assert_eq!(stats.sample_rates, [(44100, 4)]);
+        assert_eq!(stats.missing_tags, 1);
+        assert_eq!(stats.missing_gain, 2);
+        assert_eq!(stats.top_artists[0], ("A".to_string(), 2));
+
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GB");
+    }
+}
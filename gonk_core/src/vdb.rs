@@ -4,10 +4,14 @@
 //!
 //! Also contains code for querying artists, albums and songs.
 //!
-use crate::db::{Album, Song};
-use crate::{database_path, strsim, Deserialize};
-use std::collections::BTreeMap;
-use std::{cmp::Ordering, fs, str::from_utf8_unchecked};
+use crate::art::{self, Art};
+use crate::db::{self, Album, Song};
+use crate::strsim;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering::SeqCst};
+use std::sync::{Arc, Mutex};
+use std::thread;
 
 #[cfg(test)]
 mod tests {
@@ -26,7 +30,7 @@ const MIN_ACCURACY: f64 = 0.70;
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Item {
     ///(Artist, Album, Name, Disc Number, Track Number)
-    Song((String, String, String, u8, u8)),
+    Song((String, String, String, u16, u16)),
     ///(Artist, Album)
     Album((String, String)),
     ///(Artist)
@@ -52,22 +56,22 @@ fn jaro(query: &str, input: Item) -> Result<(Item, f64), (Item, f64)> {
 pub struct Database {
     btree: BTreeMap<String, Vec<Album>>,
     pub len: usize,
+    ///(Artist, Album) to cover, `None` if the album doesn't have one.
+    art: Arc<Mutex<HashMap<(String, String), Option<Arc<Art>>>>>,
+    ///Only one cover is loaded at a time.
+    loading: Arc<AtomicBool>,
 }
 
 impl Database {
     ///Read the database from disk and load it into memory.
     pub fn new() -> Self {
-        let bytes = match fs::read(database_path()) {
-            Ok(bytes) => bytes,
-            Err(error) => match error.kind() {
-                std::io::ErrorKind::NotFound => Vec::new(),
-                _ => panic!("{error}"),
-            },
+        let songs: Vec<Song> = match db::read() {
+            Ok(songs) => songs,
+            Err(err) => {
+                crate::log!("Failed to read database: {err}");
+                Vec::new()
+            }
         };
-        let songs: Vec<Song> = unsafe { from_utf8_unchecked(&bytes) }
-            .lines()
-            .flat_map(Song::deserialize)
-            .collect();
 
         let len = songs.len();
         let mut btree: BTreeMap<String, Vec<Album>> = BTreeMap::new();
@@ -105,7 +109,12 @@ impl Database {
             albums.sort_unstable_by_key(|album| album.title.to_ascii_lowercase());
         });
 
-        Self { btree, len }
+        Self {
+            btree,
+            len,
+            art: Arc::new(Mutex::new(HashMap::new())),
+            loading: Arc::new(AtomicBool::new(false)),
+        }
     }
 
     ///Get all artist names.
@@ -132,8 +141,43 @@ impl Database {
         panic!("Could not find album {} {}", artist, album);
     }
 
+    ///Get the cover of an album, `None` until it's been loaded.
+    ///
+    ///Covers are loaded on another thread. While one is loading, other albums
+    ///are skipped so scrolling past them doesn't queue them up.
+    pub fn art(&self, artist: &str, album: &str) -> Option<Arc<Art>> {
+        let key = (artist.to_string(), album.to_string());
+        if let Some(art) = self.art.lock().unwrap().get(&key) {
+            return art.clone();
+        }
+
+        if self.loading.swap(true, SeqCst) {
+            return None;
+        }
+
+        let Some(song) = self
+            .btree
+            .get(artist)
+            .and_then(|albums| albums.iter().find(|al| al.title == album))
+            .and_then(|album| album.songs.first())
+            .cloned()
+        else {
+            self.loading.store(false, SeqCst);
+            return None;
+        };
+
+        let cache = self.art.clone();
+        let loading = self.loading.clone();
+        thread::spawn(move || {
+            let art = art::load(&song).map(Arc::new);
+            cache.lock().unwrap().insert(key, art);
+            loading.store(false, SeqCst);
+        });
+        None
+    }
+
     ///Get an individual song in the database.
-    pub fn song(&self, artist: &str, album: &str, disc: u8, number: u8) -> &Song {
+    pub fn song(&self, artist: &str, album: &str, disc: u16, number: u16) -> &Song {
         for al in self.btree.get(artist).unwrap() {
             if al.title == album {
                 for song in &al.songs {
@@ -0,0 +1,353 @@
+//! Directory walker used when scanning the library.
+//!
+//! Files and folders can be excluded with glob patterns, either globally
+//! or with a `.gonkignore` file which applies to the folder it's in.
+//!
+//! - `*` matches anything except `/`
+//! - `**` matches anything, including `/`
+//! - `?` matches a single character
+//! - `\` matches the next character literally
+//!
+//! Patterns without a `/` match the file or folder name, e.g. `*.demo.flac`.
+//! Patterns with a `/` match the path relative to the library folder
+//! (or the folder containing the `.gonkignore`), e.g. `**/Samples/**`.
+//! A leading `/` makes a name only match at the top, e.g. `/01.flac`.
+//! A trailing `/` only matches folders. Lines starting with `#` are comments.
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub const IGNORE_FILE: &str = ".gonkignore";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    ///Global ignore patterns.
+    pub ignore: Vec<String>,
+    pub follow_symlinks: bool,
+    pub skip_hidden: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            ignore: Vec::new(),
+            follow_symlinks: false,
+            skip_hidden: true,
+        }
+    }
+}
+
+impl From<&crate::settings::Settings> for Options {
+    fn from(settings: &crate::settings::Settings) -> Self {
+        Self {
+            ignore: settings.ignore.clone(),
+            follow_symlinks: settings.follow_symlinks,
+            skip_hidden: settings.skip_hidden,
+        }
+    }
+}
+
+struct Rule {
+    base: PathBuf,
+    pattern: String,
+}
+
+///Find every file under `root` that isn't excluded.
+pub fn walk(root: &Path, options: &Options) -> Vec<PathBuf> {
+    let mut rules = global_rules(root, options);
+    let mut visited = HashSet::new();
+    let mut files = Vec::new();
+    walk_dir(root, options, &mut rules, &mut visited, &mut files);
+    files
+}
+
+fn walk_dir(
+    dir: &Path,
+    options: &Options,
+    rules: &mut Vec<Rule>,
+    visited: &mut HashSet<PathBuf>,
+    files: &mut Vec<PathBuf>,
+) {
+    //Symlinks can point to a parent folder.
+    if options.follow_symlinks {
+        let Ok(path) = fs::canonicalize(dir) else {
+            return;
+        };
+        if !visited.insert(path) {
+            return;
+        }
+    }
+
+    let len = rules.len();
+    read_ignore_file(dir, rules);
+
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        let path = entry.path();
+
+        let is_dir = if file_type.is_symlink() {
+            if !options.follow_symlinks {
+                continue;
+            }
+            match fs::metadata(&path) {
+                Ok(metadata) => metadata.is_dir(),
+                //Broken link.
+                Err(_) => continue,
+            }
+        } else {
+            file_type.is_dir()
+        };
+
+        if is_dir && options.skip_hidden && is_hidden(&path) {
+            continue;
+        }
+        if ignored(rules, &path, is_dir) {
+            continue;
+        }
+
+        if is_dir {
+            walk_dir(&path, options, rules, visited, files);
+        } else {
+            files.push(path);
+        }
+    }
+
+    rules.truncate(len);
+}
+
+///Check if a path inside `root` would be skipped by `walk`.
+pub fn excluded(root: &Path, path: &Path, options: &Options) -> bool {
+    let Ok(relative) = path.strip_prefix(root) else {
+        return false;
+    };
+    let mut rules = global_rules(root, options);
+    let components: Vec<_> = relative.components().collect();
+    let mut dir = root.to_path_buf();
+
+    for (i, component) in components.iter().enumerate() {
+        read_ignore_file(&dir, &mut rules);
+        let child = dir.join(component);
+
+        if !options.follow_symlinks
+            && fs::symlink_metadata(&child).is_ok_and(|m| m.file_type().is_symlink())
+        {
+            return true;
+        }
+
+        let is_dir = i + 1 < components.len() || child.is_dir();
+        if is_dir && options.skip_hidden && is_hidden(&child) {
+            return true;
+        }
+        if ignored(&rules, &child, is_dir) {
+            return true;
+        }
+        dir = child;
+    }
+
+    false
+}
+
+fn global_rules(root: &Path, options: &Options) -> Vec<Rule> {
+    options
+        .ignore
+        .iter()
+        .map(|pattern| Rule {
+            base: root.to_path_buf(),
+            pattern: pattern.clone(),
+        })
+        .collect()
+}
+
+fn read_ignore_file(dir: &Path, rules: &mut Vec<Rule>) {
+    if let Ok(text) = fs::read_to_string(dir.join(IGNORE_FILE)) {
+        for line in text.lines().map(str::trim) {
+            if !line.is_empty() && !line.starts_with('#') {
+                rules.push(Rule {
+                    base: dir.to_path_buf(),
+                    pattern: line.to_string(),
+                });
+            }
+        }
+    }
+}
+
+fn is_hidden(path: &Path) -> bool {
+    if path
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+    {
+        return true;
+    }
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+        if let Ok(metadata) = fs::symlink_metadata(path) {
+            return metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0;
+        }
+    }
+
+    false
+}
+
+fn ignored(rules: &[Rule], path: &Path, is_dir: bool) -> bool {
+    rules
+        .iter()
+        .any(|rule| match path.strip_prefix(&rule.base) {
+            Ok(relative) => {
+                let relative = relative.to_string_lossy().replace('\\', "/");
+                matches(&rule.pattern, &relative, is_dir)
+            }
+            Err(_) => false,
+        })
+}
+
+///Check if a path relative to the pattern's folder matches, separators must be `/`.
+pub fn matches(pattern: &str, path: &str, is_dir: bool) -> bool {
+    let dir_only = pattern.ends_with('/');
+    let anchored = pattern.starts_with('/');
+    let pattern = pattern.trim_end_matches('/').trim_start_matches('/');
+    if dir_only && !is_dir {
+        return false;
+    }
+
+    if anchored || pattern.contains('/') {
+        glob(pattern.as_bytes(), path.as_bytes())
+            || (is_dir && glob(pattern.as_bytes(), format!("{path}/").as_bytes()))
+    } else {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        glob(pattern.as_bytes(), name.as_bytes())
+    }
+}
+
+fn glob(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [b'*', b'*', b'/', rest @ ..] => {
+            //Zero or more folders.
+            glob(rest, text)
+                || text
+                    .iter()
+                    .enumerate()
+                    .any(|(i, c)| *c == b'/' && glob(rest, &text[i + 1..]))
+        }
+        [b'*', b'*', rest @ ..] => (0..=text.len()).any(|i| glob(rest, &text[i..])),
+        [b'*', rest @ ..] => {
+            let end = text.iter().position(|c| *c == b'/').unwrap_or(text.len());
+            (0..=end).any(|i| glob(rest, &text[i..]))
+        }
+        [b'\\', p, rest @ ..] => match text {
+            [c, text @ ..] if eq(*p, *c) => glob(rest, text),
+            _ => false,
+        },
+        [b'?', rest @ ..] => match text {
+            [c, text @ ..] if *c != b'/' => glob(rest, text),
+            _ => false,
+        },
+        [p, rest @ ..] => match text {
+            [c, text @ ..] if eq(*p, *c) => glob(rest, text),
+            _ => false,
+        },
+    }
+}
+
+///A pattern that only matches this path, relative to the library folder.
+pub fn anchored(relative: &Path) -> String {
+    let mut pattern = String::from("/");
+    for c in relative.to_string_lossy().replace('\\', "/").chars() {
+        if matches!(c, '*' | '?') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern
+}
+
+//Paths on Windows aren't case sensitive.
+fn eq(a: u8, b: u8) -> bool {
+    if cfg!(windows) {
+        a.eq_ignore_ascii_case(&b)
+    } else {
+        a == b
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns() {
+        assert!(matches("*.demo.flac", "Artist/Album/01.demo.flac", false));
+        assert!(!matches("*.demo.flac", "Artist/Album/01.flac", false));
+
+        assert!(matches("**/Samples/**", "Samples/kick.flac", false));
+        assert!(matches("**/Samples/**", "Packs/Samples/kick.flac", false));
+        assert!(matches("**/Samples/**", "Packs/Samples", true));
+        assert!(!matches("**/Samples/**", "Packs/Samples.flac", false));
+
+        assert!(matches("Backup/", "Music/Backup", true));
+        assert!(!matches("Backup/", "Music/Backup", false));
+        assert!(matches("Artist/*/0?.flac", "Artist/Album/01.flac", false));
+        assert!(!matches(
+            "Artist/*/0?.flac",
+            "Artist/Album/Disc 1/01.flac",
+            false
+        ));
+
+        let pattern = anchored(Path::new("01.flac"));
+        assert_eq!(pattern, "/01.flac");
+        assert!(matches(&pattern, "01.flac", false));
+        assert!(!matches(&pattern, "Album/01.flac", false));
+
+        let pattern = anchored(Path::new("Album/Why?*.flac"));
+        assert_eq!(pattern, "/Album/Why\\?\\*.flac");
+        assert!(matches(&pattern, "Album/Why?*.flac", false));
+        assert!(!matches(&pattern, "Album/Whys.flac", false));
+    }
+
+    #[test]
+    fn walk() {
+        let root = std::env::temp_dir().join("gonk_walk");
+        let _ = fs::remove_dir_all(&root);
+        for dir in ["Album", "Album/Samples", ".hidden", "Skipped"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in [
+            "Album/01.flac",
+            "Album/02.demo.flac",
+            "Album/Samples/kick.flac",
+            ".hidden/01.flac",
+            "Skipped/01.flac",
+        ] {
+            fs::write(root.join(file), b"").unwrap();
+        }
+        fs::write(root.join("Skipped").join(IGNORE_FILE), "*").unwrap();
+
+        let options = Options {
+            ignore: vec!["**/Samples/**".to_string(), "*.demo.flac".to_string()],
+            ..Options::default()
+        };
+        let files = super::walk(&root, &options);
+        assert_eq!(files, [root.join("Album").join("01.flac")]);
+
+        assert!(excluded(&root, &root.join("Skipped/02.flac"), &options));
+        assert!(excluded(
+            &root,
+            &root.join("Album/Samples/snare.flac"),
+            &options
+        ));
+        assert!(!excluded(&root, &root.join("Album/03.flac"), &options));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
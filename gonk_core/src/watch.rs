@@ -0,0 +1,418 @@
+//! Watch the music folders for changes.
+//!
+//! Uses inotify on Linux and falls back to polling file sizes and modification times elsewhere.
+//! Changes are collected until the folder has been quiet for `DEBOUNCE`
+//! then sent as a single batch of paths, see `db::update`.
+use crate::walk;
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+const DEBOUNCE: Duration = Duration::from_secs(1);
+const SLEEP: Duration = Duration::from_millis(250);
+
+pub struct Watcher {
+    pub folders: Vec<String>,
+    pub options: walk::Options,
+    receiver: Receiver<Vec<PathBuf>>,
+    stop: Arc<AtomicBool>,
+    ready: Arc<AtomicBool>,
+}
+
+impl Watcher {
+    pub fn new(folders: &[String], options: walk::Options) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let ready = Arc::new(AtomicBool::new(false));
+        let roots = folders.iter().map(PathBuf::from).collect();
+        let s = Arc::clone(&stop);
+        let r = Arc::clone(&ready);
+        let o = options.clone();
+        thread::spawn(move || run(roots, o, sender, s, r));
+
+        Self {
+            folders: folders.to_vec(),
+            options,
+            receiver,
+            stop,
+            ready,
+        }
+    }
+
+    ///True once the folders are being watched, changes before this might be missed.
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Relaxed)
+    }
+
+    ///Paths that have been created, modified, deleted or moved since the last call.
+    pub fn changes(&self) -> Option<Vec<PathBuf>> {
+        let mut paths: Vec<PathBuf> = self.receiver.try_iter().flatten().collect();
+        if paths.is_empty() {
+            return None;
+        }
+        paths.sort();
+        paths.dedup();
+        Some(paths)
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+//Groups events that happen close together.
+#[derive(Default)]
+struct Batch {
+    paths: HashSet<PathBuf>,
+    last: Option<Instant>,
+}
+
+impl Batch {
+    fn push(&mut self, path: PathBuf) {
+        self.paths.insert(path);
+        self.last = Some(Instant::now());
+    }
+
+    ///Returns false if the receiver has been dropped.
+    fn send(&mut self, sender: &Sender<Vec<PathBuf>>) -> bool {
+        match self.last {
+            Some(last) if last.elapsed() >= DEBOUNCE => {
+                self.last = None;
+                sender.send(self.paths.drain().collect()).is_ok()
+            }
+            _ => true,
+        }
+    }
+}
+
+//Excluded paths are filtered out by `db::update`.
+#[cfg(target_os = "linux")]
+fn run(
+    roots: Vec<PathBuf>,
+    options: walk::Options,
+    sender: Sender<Vec<PathBuf>>,
+    stop: Arc<AtomicBool>,
+    ready: Arc<AtomicBool>,
+) {
+    let Some(mut inotify) = inotify::Inotify::new(options.clone()) else {
+        crate::log!("Failed to watch folders, falling back to polling.");
+        return poll::run(roots, options, sender, stop, ready);
+    };
+    for root in &roots {
+        inotify.add_recursive(root, root);
+    }
+    ready.store(true, Ordering::Relaxed);
+
+    let mut batch = Batch::default();
+    while !stop.load(Ordering::Relaxed) {
+        for path in inotify.read(&roots) {
+            batch.push(path);
+        }
+        if !batch.send(&sender) {
+            return;
+        }
+        thread::sleep(SLEEP);
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn run(
+    roots: Vec<PathBuf>,
+    options: walk::Options,
+    sender: Sender<Vec<PathBuf>>,
+    stop: Arc<AtomicBool>,
+    ready: Arc<AtomicBool>,
+) {
+    poll::run(roots, options, sender, stop, ready)
+}
+
+#[cfg(target_os = "linux")]
+mod inotify {
+    use crate::walk;
+    use std::{
+        collections::HashMap,
+        ffi::{c_char, c_int, c_void, CString, OsStr},
+        fs,
+        os::unix::ffi::OsStrExt,
+        path::{Path, PathBuf},
+    };
+
+    extern "C" {
+        fn inotify_init1(flags: c_int) -> c_int;
+        fn inotify_add_watch(fd: c_int, path: *const c_char, mask: u32) -> c_int;
+        fn read(fd: c_int, buf: *mut c_void, count: usize) -> isize;
+        fn close(fd: c_int) -> c_int;
+    }
+
+    //https://man7.org/linux/man-pages/man7/inotify.7.html
+    const IN_NONBLOCK: c_int = 0o4000;
+    const IN_CLOEXEC: c_int = 0o2000000;
+    const IN_CLOSE_WRITE: u32 = 0x8;
+    const IN_MOVED_FROM: u32 = 0x40;
+    const IN_MOVED_TO: u32 = 0x80;
+    const IN_CREATE: u32 = 0x100;
+    const IN_DELETE: u32 = 0x200;
+    const IN_DELETE_SELF: u32 = 0x400;
+    const IN_Q_OVERFLOW: u32 = 0x4000;
+    const IN_IGNORED: u32 = 0x8000;
+    const IN_ISDIR: u32 = 0x4000_0000;
+    const MASK: u32 =
+        IN_CLOSE_WRITE | IN_MOVED_FROM | IN_MOVED_TO | IN_CREATE | IN_DELETE | IN_DELETE_SELF;
+
+    //wd, mask, cookie, len
+    const HEADER: usize = 16;
+
+    pub struct Inotify {
+        fd: c_int,
+        //Watch descriptor to directory.
+        pub(super) watches: HashMap<c_int, PathBuf>,
+        buffer: Vec<u8>,
+        options: walk::Options,
+    }
+
+    impl Inotify {
+        pub fn new(options: walk::Options) -> Option<Self> {
+            let fd = unsafe { inotify_init1(IN_NONBLOCK | IN_CLOEXEC) };
+            (fd >= 0).then(|| Self {
+                fd,
+                watches: HashMap::new(),
+                buffer: vec![0; 64 * 1024],
+                options,
+            })
+        }
+
+        ///Watch the directory and every directory inside it that isn't excluded.
+        ///
+        ///Each directory uses up one of the user's inotify watches.
+        pub fn add_recursive(&mut self, root: &Path, dir: &Path) {
+            let Ok(path) = CString::new(dir.as_os_str().as_bytes()) else {
+                return;
+            };
+            let wd = unsafe { inotify_add_watch(self.fd, path.as_ptr(), MASK) };
+            if wd < 0 {
+                return;
+            }
+            self.watches.insert(wd, dir.to_path_buf());
+
+            if let Ok(entries) = fs::read_dir(dir) {
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    if entry.file_type().is_ok_and(|t| t.is_dir())
+                        && !walk::excluded(root, &path, &self.options)
+                    {
+                        self.add_recursive(root, &path);
+                    }
+                }
+            }
+        }
+
+        ///Read all pending events and return the paths that changed.
+        pub fn read(&mut self, roots: &[PathBuf]) -> Vec<PathBuf> {
+            let mut paths = Vec::new();
+
+            loop {
+                let len = unsafe {
+                    read(
+                        self.fd,
+                        self.buffer.as_mut_ptr() as *mut c_void,
+                        self.buffer.len(),
+                    )
+                };
+                if len <= 0 {
+                    break;
+                }
+
+                let mut events = Vec::new();
+                let mut i = 0;
+                while i + HEADER <= len as usize {
+                    let field = |at: usize| {
+                        u32::from_ne_bytes(self.buffer[i + at..i + at + 4].try_into().unwrap())
+                    };
+                    let wd = field(0) as c_int;
+                    let mask = field(4);
+                    let name_len = field(12) as usize;
+                    let name = &self.buffer[i + HEADER..i + HEADER + name_len];
+                    //The name is padded with null bytes.
+                    let end = name.iter().position(|b| *b == 0).unwrap_or(name.len());
+                    events.push((wd, mask, OsStr::from_bytes(&name[..end]).to_os_string()));
+                    i += HEADER + name_len;
+                }
+
+                for (wd, mask, name) in events {
+                    if mask & IN_Q_OVERFLOW != 0 {
+                        //Some events were lost, rescan everything.
+                        paths.extend_from_slice(roots);
+                        continue;
+                    }
+                    //The directory was deleted or unmounted, its parent reports the change.
+                    if mask & (IN_DELETE_SELF | IN_IGNORED) != 0 {
+                        self.watches.remove(&wd);
+                        continue;
+                    }
+                    let Some(dir) = self.watches.get(&wd) else {
+                        continue;
+                    };
+                    let path = dir.join(name);
+
+                    if mask & IN_ISDIR != 0 && mask & (IN_CREATE | IN_MOVED_TO) != 0 {
+                        //Folders can be nested, the closest one decides what's excluded.
+                        let root = roots
+                            .iter()
+                            .filter(|root| path.starts_with(root))
+                            .max_by_key(|root| root.as_os_str().len());
+                        if let Some(root) = root {
+                            if !walk::excluded(root, &path, &self.options) {
+                                self.add_recursive(root, &path);
+                            }
+                        }
+                    }
+
+                    //Wait until new files have been written.
+                    if mask & IN_CREATE != 0 && mask & IN_ISDIR == 0 {
+                        continue;
+                    }
+
+                    paths.push(path);
+                }
+            }
+
+            paths
+        }
+    }
+
+    impl Drop for Inotify {
+        fn drop(&mut self) {
+            unsafe { close(self.fd) };
+        }
+    }
+}
+
+mod poll {
+    use super::*;
+    use crate::db::is_audio;
+    use std::{collections::HashMap, fs, time::SystemTime};
+
+    const INTERVAL: Duration = Duration::from_secs(30);
+
+    fn snapshot(
+        roots: &[PathBuf],
+        options: &walk::Options,
+    ) -> HashMap<PathBuf, (u64, Option<SystemTime>)> {
+        roots
+            .iter()
+            .flat_map(|root| walk::walk(root, options))
+            .filter(|path| is_audio(path))
+            .map(|path| {
+                let metadata = fs::metadata(&path).ok();
+                let size = metadata.as_ref().map(|m| m.len()).unwrap_or_default();
+                let modified = metadata.and_then(|m| m.modified().ok());
+                (path, (size, modified))
+            })
+            .collect()
+    }
+
+    pub fn run(
+        roots: Vec<PathBuf>,
+        options: walk::Options,
+        sender: Sender<Vec<PathBuf>>,
+        stop: Arc<AtomicBool>,
+        ready: Arc<AtomicBool>,
+    ) {
+        let mut previous = snapshot(&roots, &options);
+        let mut last = Instant::now();
+        ready.store(true, Ordering::Relaxed);
+
+        while !stop.load(Ordering::Relaxed) {
+            thread::sleep(SLEEP);
+            if last.elapsed() < INTERVAL {
+                continue;
+            }
+            last = Instant::now();
+
+            let current = snapshot(&roots, &options);
+            let mut paths: Vec<PathBuf> = current
+                .iter()
+                .filter(|(path, stamp)| previous.get(*path) != Some(stamp))
+                .map(|(path, _)| path.clone())
+                .collect();
+            paths.extend(
+                previous
+                    .keys()
+                    .filter(|path| !current.contains_key(*path))
+                    .cloned(),
+            );
+            previous = current;
+
+            if !paths.is_empty() && sender.send(paths).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watch() {
+        let dir = std::env::temp_dir().join("gonk_watch");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let watcher = Watcher::new(&[dir.to_string_lossy().to_string()], Default::default());
+        let now = Instant::now();
+        while !watcher.is_ready() {
+            assert!(now.elapsed() < Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(1));
+        }
+        std::fs::create_dir(dir.join("album")).unwrap();
+        std::fs::write(dir.join("album").join("01.flac"), b"").unwrap();
+
+        let now = Instant::now();
+        let mut changes = Vec::new();
+        while now.elapsed() < Duration::from_secs(5) && changes.is_empty() {
+            thread::sleep(SLEEP);
+            changes = watcher.changes().unwrap_or_default();
+        }
+        assert!(changes.contains(&dir.join("album")));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn watches() {
+        let dir = std::env::temp_dir().join("gonk_watches");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join(".git")).unwrap();
+        std::fs::create_dir_all(dir.join("Samples")).unwrap();
+        std::fs::create_dir_all(dir.join("album")).unwrap();
+
+        let options = walk::Options {
+            ignore: vec!["Samples".to_string()],
+            ..Default::default()
+        };
+        let mut inotify = inotify::Inotify::new(options).unwrap();
+        inotify.add_recursive(&dir, &dir);
+        assert_eq!(inotify.watches.len(), 2);
+
+        //Removed folders stop being watched.
+        std::fs::remove_dir(dir.join("album")).unwrap();
+        let changes = inotify.read(std::slice::from_ref(&dir));
+        assert!(changes.contains(&dir.join("album")));
+        assert_eq!(inotify.watches.len(), 1);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
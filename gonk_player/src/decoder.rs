@@ -1,4 +1,5 @@
 //! Decoder for audio files.
+use crate::{read_ahead, READ_AHEAD};
 use std::io::ErrorKind;
 use std::time::Duration;
 use std::{fs::File, path::Path};
@@ -9,7 +10,7 @@ use symphonia::{
         audio::SampleBuffer,
         codecs,
         formats::{FormatOptions, SeekMode, SeekTo},
-        io::MediaSourceStream,
+        io::{MediaSource, MediaSourceStream},
         meta::MetadataOptions,
         probe::Hint,
         units::Time,
@@ -29,8 +30,12 @@ pub struct Symphonia {
 
 impl Symphonia {
     pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
-        let file = File::open(path)?;
-        let mss = MediaSourceStream::new(Box::new(file), Default::default());
+        let source: Box<dyn MediaSource> = if unsafe { READ_AHEAD } {
+            read_ahead::open(path.as_ref())?
+        } else {
+            Box::new(File::open(path)?)
+        };
+        let mss = MediaSourceStream::new(source, Default::default());
         let probed = get_probe().format(
             &Hint::default(),
             mss,
@@ -71,6 +76,13 @@ impl Symphonia {
     pub fn sample_rate(&self) -> u32 {
         self.track.codec_params.sample_rate.unwrap()
     }
+    pub fn channels(&self) -> usize {
+        self.track
+            .codec_params
+            .channels
+            .map(|channels| channels.count())
+            .unwrap_or(2)
+    }
     //TODO: I would like seeking out of bounds to play the next song.
     //I can't trust symphonia to provide accurate errors so it's not worth the hassle.
     //I could use pos + elapsed > duration but the duration isn't accurate.
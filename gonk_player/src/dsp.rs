@@ -0,0 +1,385 @@
+//! DSP effect chain
+//!
+//! Each effect processes interleaved 32-bit float blocks in place.
+//! The player owns a chain that runs after the gain stage and before the output.
+//! By default it contains channel swap, mono, balance, crossfeed, preamp and the limiter.
+//! Swap, mono and crossfeed start disabled.
+//!
+//! The audio thread never waits on a lock, so the chain is changed by queueing edits
+//! which are applied before the next block is processed.
+//!
+//! ```ignore
+//! struct Invert;
+//!
+//! impl Dsp for Invert {
+//!     fn name(&self) -> &str {
+//!         "invert"
+//!     }
+//!     fn process(&mut self, samples: &mut [f32], _sample_rate: u32, _channels: usize) {
+//!         samples.iter_mut().for_each(|sample| *sample = -*sample);
+//!     }
+//! }
+//!
+//! gonk_player::dsp::edit(|chain| {
+//!     let limiter = chain.position("limiter").unwrap_or(chain.len());
+//!     chain.insert(limiter, Box::new(Invert));
+//! });
+//! ```
+use crate::limiter::Limiter;
+use crossbeam_queue::SegQueue;
+
+pub type Edit = Box<dyn FnOnce(&mut Chain) + Send>;
+
+static EDITS: SegQueue<Edit> = SegQueue::new();
+
+///Change the chain used for playback.
+pub fn edit<F: FnOnce(&mut Chain) + Send + 'static>(edit: F) {
+    EDITS.push(Box::new(edit));
+}
+
+///Apply every queued edit to the chain.
+pub fn apply_edits(chain: &mut Chain) {
+    while let Some(edit) = EDITS.pop() {
+        edit(chain);
+    }
+}
+
+pub trait Dsp: Send {
+    ///Used to find the effect in the chain, should be unique.
+    fn name(&self) -> &str;
+    ///Process a block of interleaved samples in place.
+    fn process(&mut self, samples: &mut [f32], sample_rate: u32, channels: usize);
+    ///Clear any internal state, called on seek and track change.
+    fn reset(&mut self) {}
+}
+
+struct Effect {
+    dsp: Box<dyn Dsp>,
+    enabled: bool,
+}
+
+///An ordered list of effects.
+pub struct Chain {
+    effects: Vec<Effect>,
+}
+
+impl Default for Chain {
+    fn default() -> Self {
+        let mut chain = Chain::new();
+        chain.push(Box::new(SwapChannels));
+        chain.push(Box::new(Mono));
+        chain.push(Box::new(Balance::new(0.0)));
+        chain.push(Box::new(Crossfeed::new()));
+        chain.push(Box::new(Preamp::new(0.0)));
+        chain.push(Box::new(Limiter::new()));
+        chain.set_enabled("swap", false);
+        chain.set_enabled("mono", false);
+        chain.set_enabled("crossfeed", false);
+        chain
+    }
+}
+
+impl Chain {
+    ///Create an empty chain.
+    pub const fn new() -> Self {
+        Self {
+            effects: Vec::new(),
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.effects.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.effects.is_empty()
+    }
+    pub fn names(&self) -> Vec<&str> {
+        self.effects
+            .iter()
+            .map(|effect| effect.dsp.name())
+            .collect()
+    }
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.effects
+            .iter()
+            .position(|effect| effect.dsp.name() == name)
+    }
+    pub fn push(&mut self, dsp: Box<dyn Dsp>) {
+        self.effects.push(Effect { dsp, enabled: true });
+    }
+    pub fn insert(&mut self, index: usize, dsp: Box<dyn Dsp>) {
+        self.effects.insert(index, Effect { dsp, enabled: true });
+    }
+    pub fn remove(&mut self, name: &str) -> Option<Box<dyn Dsp>> {
+        let index = self.position(name)?;
+        Some(self.effects.remove(index).dsp)
+    }
+    ///Swap an effect for a new one with the same name, keeping it's position.
+    ///The effect is added to the end of the chain if it doesn't exist.
+    pub fn replace(&mut self, dsp: Box<dyn Dsp>) {
+        match self.position(dsp.name()) {
+            Some(index) => self.effects[index].dsp = dsp,
+            None => self.push(dsp),
+        }
+    }
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) {
+        if let Some(index) = self.position(name) {
+            let effect = &mut self.effects[index];
+            if !enabled {
+                effect.dsp.reset();
+            }
+            effect.enabled = enabled;
+        }
+    }
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.position(name)
+            .is_some_and(|index| self.effects[index].enabled)
+    }
+    pub fn process(&mut self, samples: &mut [f32], sample_rate: u32, channels: usize) {
+        for effect in &mut self.effects {
+            if effect.enabled {
+                effect.dsp.process(samples, sample_rate, channels);
+            }
+        }
+    }
+    pub fn reset(&mut self) {
+        for effect in &mut self.effects {
+            effect.dsp.reset();
+        }
+    }
+}
+
+///Amplify or reduce the volume before the limiter.
+pub struct Preamp {
+    gain: f32,
+}
+
+impl Preamp {
+    pub fn new(db: f32) -> Self {
+        Self {
+            gain: 10.0f32.powf(db / 20.0),
+        }
+    }
+}
+
+impl Dsp for Preamp {
+    fn name(&self) -> &str {
+        "preamp"
+    }
+    fn process(&mut self, samples: &mut [f32], _sample_rate: u32, _channels: usize) {
+        for sample in samples {
+            *sample *= self.gain;
+        }
+    }
+}
+
+///Swap the left and right channels.
+pub struct SwapChannels;
+
+impl Dsp for SwapChannels {
+    fn name(&self) -> &str {
+        "swap"
+    }
+    fn process(&mut self, samples: &mut [f32], _sample_rate: u32, channels: usize) {
+        if channels < 2 {
+            return;
+        }
+        for frame in samples.chunks_exact_mut(channels) {
+            frame.swap(0, 1);
+        }
+    }
+}
+
+///Mix the left and right channels together.
+pub struct Mono;
+
+impl Dsp for Mono {
+    fn name(&self) -> &str {
+        "mono"
+    }
+    fn process(&mut self, samples: &mut [f32], _sample_rate: u32, channels: usize) {
+        if channels < 2 {
+            return;
+        }
+        for frame in samples.chunks_exact_mut(channels) {
+            let mid = (frame[0] + frame[1]) * 0.5;
+            frame[0] = mid;
+            frame[1] = mid;
+        }
+    }
+}
+
+///Left/right balance from -1.0 (left) to 1.0 (right).
+///
+///The quieter side is reduced, the louder side stays at full volume.
+pub struct Balance {
+    left: f32,
+    right: f32,
+}
+
+impl Balance {
+    pub fn new(balance: f32) -> Self {
+        let balance = balance.clamp(-1.0, 1.0);
+        Self {
+            left: (1.0 - balance).min(1.0),
+            right: (1.0 + balance).min(1.0),
+        }
+    }
+}
+
+impl Dsp for Balance {
+    fn name(&self) -> &str {
+        "balance"
+    }
+    fn process(&mut self, samples: &mut [f32], _sample_rate: u32, channels: usize) {
+        if channels < 2 {
+            return;
+        }
+        for frame in samples.chunks_exact_mut(channels) {
+            frame[0] *= self.left;
+            frame[1] *= self.right;
+        }
+    }
+}
+
+//Bauer crossfeed, the same defaults as bs2b.
+const CROSSFEED_CUTOFF: f32 = 700.0;
+const CROSSFEED_LEVEL: f32 = 4.5;
+
+///Headphone crossfeed for hard panned recordings.
+///
+///Low frequencies from each channel are fed into the other, like listening to speakers.
+///The direct signal has it's high frequencies boosted to match,
+///so a centered (mono) signal passes through unchanged.
+pub struct Crossfeed {
+    sample_rate: u32,
+    coefficient: f32,
+    gain: f32,
+    //Low passed left and right channels.
+    low: [f32; 2],
+}
+
+impl Default for Crossfeed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Crossfeed {
+    pub fn new() -> Self {
+        Self {
+            sample_rate: 0,
+            coefficient: 0.0,
+            gain: 10.0f32.powf(-CROSSFEED_LEVEL / 20.0),
+            low: [0.0; 2],
+        }
+    }
+}
+
+impl Dsp for Crossfeed {
+    fn name(&self) -> &str {
+        "crossfeed"
+    }
+    fn process(&mut self, samples: &mut [f32], sample_rate: u32, channels: usize) {
+        if channels < 2 {
+            return;
+        }
+        if sample_rate != self.sample_rate {
+            self.sample_rate = sample_rate;
+            self.coefficient =
+                1.0 - (-2.0 * std::f32::consts::PI * CROSSFEED_CUTOFF / sample_rate as f32).exp();
+            self.reset();
+        }
+
+        let gain = self.gain;
+        let norm = 1.0 / (1.0 + gain);
+
+        for frame in samples.chunks_exact_mut(channels) {
+            let (left, right) = (frame[0], frame[1]);
+            self.low[0] += self.coefficient * (left - self.low[0]);
+            self.low[1] += self.coefficient * (right - self.low[1]);
+            let [low_left, low_right] = self.low;
+
+            let direct_left = low_left + (left - low_left) * (1.0 + gain);
+            let direct_right = low_right + (right - low_right) * (1.0 + gain);
+
+            frame[0] = (direct_left + low_right * gain) * norm;
+            frame[1] = (direct_right + low_left * gain) * norm;
+        }
+    }
+    fn reset(&mut self) {
+        self.low = [0.0; 2];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Add(f32);
+
+    impl Dsp for Add {
+        fn name(&self) -> &str {
+            "add"
+        }
+        fn process(&mut self, samples: &mut [f32], _sample_rate: u32, _channels: usize) {
+            for sample in samples {
+                *sample += self.0;
+            }
+        }
+    }
+
+    #[test]
+    fn chain() {
+        let mut chain = Chain::new();
+        chain.push(Box::new(Preamp::new(6.0206)));
+        chain.insert(0, Box::new(Add(1.0)));
+        assert_eq!(chain.names(), ["add", "preamp"]);
+
+        //Effects run in order.
+        let mut samples = [0.0, 0.5];
+        chain.process(&mut samples, 44100, 2);
+        assert!((samples[0] - 2.0).abs() < 0.001);
+        assert!((samples[1] - 3.0).abs() < 0.001);
+
+        chain.set_enabled("preamp", false);
+        assert!(!chain.is_enabled("preamp"));
+        let mut samples = [0.0];
+        chain.process(&mut samples, 44100, 1);
+        assert_eq!(samples, [1.0]);
+
+        chain.replace(Box::new(Add(2.0)));
+        assert_eq!(chain.position("add"), Some(0));
+        assert!(chain.remove("add").is_some());
+        assert_eq!(chain.len(), 1);
+    }
+
+    #[test]
+    fn channels() {
+        let mut samples = [1.0, 0.0];
+        SwapChannels.process(&mut samples, 44100, 2);
+        assert_eq!(samples, [0.0, 1.0]);
+
+        Mono.process(&mut samples, 44100, 2);
+        assert_eq!(samples, [0.5, 0.5]);
+
+        Balance::new(-0.5).process(&mut samples, 44100, 2);
+        assert_eq!(samples, [0.5, 0.25]);
+    }
+
+    #[test]
+    fn crossfeed() {
+        let mut crossfeed = Crossfeed::new();
+
+        //Centered audio is unchanged once the filter has settled.
+        let mut samples = vec![0.5; 44100 * 2];
+        crossfeed.process(&mut samples, 44100, 2);
+        assert!((samples[samples.len() - 1] - 0.5).abs() < 0.0001);
+
+        //Hard panned low frequencies are fed into the other channel.
+        crossfeed.reset();
+        let mut samples: Vec<f32> = (0..44100).flat_map(|_| [0.5, 0.0]).collect();
+        crossfeed.process(&mut samples, 44100, 2);
+        let last = &samples[samples.len() - 2..];
+        assert!(last[1] > 0.1 && last[0] > last[1]);
+    }
+}
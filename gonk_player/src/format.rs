@@ -0,0 +1,184 @@
+//! Sample format conversion.
+//!
+//! Audio is processed as 32-bit float but output devices and files
+//! can also use 16, 24 or 32-bit integers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleFormat {
+    F32,
+    I16,
+    I24,
+    I32,
+}
+
+//https://learn.microsoft.com/en-us/windows/win32/api/mmreg/ns-mmreg-waveformatex
+const WAVE_FORMAT_PCM: u16 = 1;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+impl SampleFormat {
+    pub fn from_bits(bits: u16) -> Option<Self> {
+        match bits {
+            16 => Some(SampleFormat::I16),
+            24 => Some(SampleFormat::I24),
+            32 => Some(SampleFormat::I32),
+            _ => None,
+        }
+    }
+
+    ///Read the format from the bytes of a `WAVEFORMATEX` or `WAVEFORMATEXTENSIBLE`.
+    pub fn from_wave_format(bytes: &[u8]) -> Option<Self> {
+        let u16_le = |i: usize| Some(u16::from_le_bytes(bytes.get(i..i + 2)?.try_into().ok()?));
+
+        let mut tag = u16_le(0)?;
+        let bits = u16_le(14)?;
+
+        //The real format tag is the first two bytes of the sub format GUID.
+        if tag == WAVE_FORMAT_EXTENSIBLE {
+            tag = u16_le(24)?;
+        }
+
+        match (tag, bits) {
+            (WAVE_FORMAT_IEEE_FLOAT, 32) => Some(SampleFormat::F32),
+            (WAVE_FORMAT_PCM, bits) => SampleFormat::from_bits(bits),
+            _ => None,
+        }
+    }
+
+    pub fn format_tag(self) -> u16 {
+        match self {
+            SampleFormat::F32 => WAVE_FORMAT_IEEE_FLOAT,
+            SampleFormat::I16 | SampleFormat::I24 | SampleFormat::I32 => WAVE_FORMAT_PCM,
+        }
+    }
+
+    pub fn bits(self) -> u16 {
+        match self {
+            SampleFormat::I16 => 16,
+            SampleFormat::I24 => 24,
+            SampleFormat::F32 | SampleFormat::I32 => 32,
+        }
+    }
+
+    pub fn bytes(self) -> usize {
+        self.bits() as usize / 8
+    }
+}
+
+///Converts float samples to bytes with optional TPDF dithering.
+pub struct Converter {
+    pub format: SampleFormat,
+    pub dither: bool,
+    seed: u32,
+}
+
+impl Converter {
+    pub fn new(format: SampleFormat, dither: bool) -> Self {
+        Self {
+            format,
+            dither,
+            seed: 0x9E37_79B9,
+        }
+    }
+
+    //xorshift32, uniform in [0, 1).
+    fn random(&mut self) -> f64 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        self.seed as f64 / (u32::MAX as f64 + 1.0)
+    }
+
+    //Triangular noise between -1 and 1 LSB.
+    fn tpdf(&mut self) -> f64 {
+        self.random() - self.random()
+    }
+
+    fn quantize(&mut self, sample: f32, max: f64) -> i64 {
+        let mut scaled = sample.clamp(-1.0, 1.0) as f64 * max;
+        if self.dither {
+            scaled += self.tpdf();
+        }
+        scaled.round().clamp(-max - 1.0, max) as i64
+    }
+
+    ///Write a sample into `output`, which must be `format.bytes()` long.
+    pub fn write(&mut self, sample: f32, output: &mut [u8]) {
+        match self.format {
+            SampleFormat::F32 => output.copy_from_slice(&sample.to_le_bytes()),
+            SampleFormat::I16 => {
+                let sample = self.quantize(sample, i16::MAX as f64) as i16;
+                output.copy_from_slice(&sample.to_le_bytes());
+            }
+            SampleFormat::I24 => {
+                let sample = self.quantize(sample, 8_388_607.0) as i32;
+                output.copy_from_slice(&sample.to_le_bytes()[0..3]);
+            }
+            SampleFormat::I32 => {
+                let sample = self.quantize(sample, i32::MAX as f64) as i32;
+                output.copy_from_slice(&sample.to_le_bytes());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wave_format() {
+        let mut bytes = [0u8; 40];
+        bytes[0..2].copy_from_slice(&WAVE_FORMAT_EXTENSIBLE.to_le_bytes());
+        bytes[14..16].copy_from_slice(&32u16.to_le_bytes());
+        bytes[24..26].copy_from_slice(&WAVE_FORMAT_IEEE_FLOAT.to_le_bytes());
+        assert_eq!(
+            SampleFormat::from_wave_format(&bytes),
+            Some(SampleFormat::F32)
+        );
+
+        bytes[14..16].copy_from_slice(&24u16.to_le_bytes());
+        bytes[24..26].copy_from_slice(&WAVE_FORMAT_PCM.to_le_bytes());
+        assert_eq!(
+            SampleFormat::from_wave_format(&bytes),
+            Some(SampleFormat::I24)
+        );
+
+        bytes[0..2].copy_from_slice(&WAVE_FORMAT_PCM.to_le_bytes());
+        bytes[14..16].copy_from_slice(&16u16.to_le_bytes());
+        assert_eq!(
+            SampleFormat::from_wave_format(&bytes),
+            Some(SampleFormat::I16)
+        );
+    }
+
+    #[test]
+    fn convert() {
+        let mut converter = Converter::new(SampleFormat::I16, false);
+        let mut output = [0; 2];
+        converter.write(1.0, &mut output);
+        assert_eq!(i16::from_le_bytes(output), i16::MAX);
+        converter.write(-2.0, &mut output);
+        assert_eq!(i16::from_le_bytes(output), -i16::MAX);
+
+        let mut converter = Converter::new(SampleFormat::I24, false);
+        let mut output = [0; 3];
+        converter.write(-1.0, &mut output);
+        assert_eq!(output, [0x01, 0x00, 0x80]);
+    }
+
+    #[test]
+    fn dither() {
+        let mut converter = Converter::new(SampleFormat::I16, true);
+        let mut output = [0; 2];
+        let mut sum = 0i64;
+
+        //Dither should be within one LSB and average out to the input.
+        for _ in 0..10000 {
+            converter.write(0.0, &mut output);
+            let sample = i16::from_le_bytes(output) as i64;
+            assert!(sample.abs() <= 1);
+            sum += sample;
+        }
+        assert!(sum.abs() < 200, "{sum}");
+    }
+}
@@ -2,7 +2,13 @@
 //! TODO: Describe the audio backend
 use crossbeam_queue::SegQueue;
 use decoder::Symphonia;
-use gonk_core::{Index, Song};
+use dsp::{Balance, Chain, Preamp};
+use format::{Converter, SampleFormat};
+use gonk_core::{
+    bookmarks::{self, Bookmarks},
+    silence::Silence,
+    Index, Song,
+};
 use mini::*;
 use ringbuf::{
     traits::{Consumer, Observer, Producer, Split},
@@ -11,14 +17,23 @@ use ringbuf::{
 use std::mem::MaybeUninit;
 use std::{
     path::{Path, PathBuf},
-    sync::Once,
+    sync::{Mutex, Once},
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 use symphonia::core::audio::SampleBuffer;
 use wasapi::*;
 
 mod decoder;
+pub mod dsp;
+pub mod format;
+mod limiter;
+mod read_ahead;
+pub mod render;
+mod resample;
+mod silence;
+
+pub use render::render;
 
 //TODO: These should be configurable.
 const VOLUME_REDUCTION: f32 = 75.0;
@@ -27,6 +42,13 @@ const VOLUME_REDUCTION: f32 = 75.0;
 pub static mut RB_SIZE: usize = 4096 * 4;
 // const RB_SIZE: usize = 4096 * 4;
 
+//Bookmarks are saved after this many seconds of playback.
+const BOOKMARK_INTERVAL: f32 = 10.0;
+
+//How long to wait for the device before checking if it was removed.
+const DEVICE_TIMEOUT: u32 = 100;
+const DEVICE_TIMEOUT_DURATION: Duration = Duration::from_millis(DEVICE_TIMEOUT as u64);
+
 const COMMON_SAMPLE_RATES: [u32; 13] = [
     5512, 8000, 11025, 16000, 22050, 32000, 44100, 48000, 64000, 88200, 96000, 176400, 192000,
 ];
@@ -36,8 +58,19 @@ static mut ELAPSED: Duration = Duration::from_secs(0);
 static mut DURATION: Duration = Duration::from_secs(0);
 static mut VOLUME: f32 = 15.0 / VOLUME_REDUCTION;
 static mut GAIN: Option<f32> = None;
+static mut FADE: f32 = 1.0;
+//Set when the effects need to be reset after a seek.
+static mut RESET_DSP: bool = false;
+pub(crate) static mut READ_AHEAD: bool = false;
+static mut DITHER: bool = true;
+//Last time the limiter reduced the volume or the output clipped.
+pub(crate) static mut LIMITED: Option<Instant> = None;
+static mut CLIPPED: Option<Instant> = None;
 static mut OUTPUT_DEVICE: Option<Device> = None;
+static CURRENT_DEVICE: Mutex<String> = Mutex::new(String::new());
 static mut PAUSED: bool = false;
+static mut SKIP_SILENCE: bool = false;
+static mut CACHE_SILENCE: bool = true;
 
 //Safety: Only written on decoder thread.
 static mut NEXT: bool = false;
@@ -97,31 +130,72 @@ pub fn devices() -> Vec<Device> {
 
 ///Get the default output device.
 pub fn default_device() -> Device {
+    default_output_device().unwrap()
+}
+
+fn default_output_device() -> Result<Device, String> {
     unsafe {
         init_com();
         let device = ENUMERATOR
             .assume_init_mut()
             .GetDefaultAudioEndpoint(DataFlow::Render, Role::Console)
-            .unwrap();
-        Device {
+            .map_err(|e| format!("{e:?}"))?;
+        Ok(Device {
             name: device.name(),
             inner: device,
-        }
+        })
     }
 }
 
-pub unsafe fn create_wasapi(
-    device: &Device,
-    sample_rate: Option<u32>,
-) -> (
+type Wasapi = (
     IAudioClient,
     IAudioRenderClient,
     WAVEFORMATEXTENSIBLE,
     *mut c_void,
-) {
-    let client: IAudioClient = device.inner.Activate(ExecutionContext::All).unwrap();
-    let mut format =
-        (client.GetMixFormat().unwrap() as *const _ as *const WAVEFORMATEXTENSIBLE).read();
+);
+
+///Open the device from `set_output_device` if there is one, otherwise the default device.
+unsafe fn fallback_device(sample_rate: Option<u32>) -> Result<(Device, Wasapi), String> {
+    if let Some(new_device) = OUTPUT_DEVICE.take() {
+        match create_wasapi(&new_device, sample_rate) {
+            Ok(wasapi) => return Ok((new_device, wasapi)),
+            Err(e) => gonk_core::log!("Failed to use {}: {e}", new_device.name),
+        }
+    }
+    let default = default_output_device()?;
+    let wasapi = create_wasapi(&default, sample_rate)?;
+    Ok((default, wasapi))
+}
+
+fn set_current_device(name: &str) {
+    *CURRENT_DEVICE.lock().unwrap() = name.to_string();
+}
+
+///Name of the device that is currently playing audio.
+///This will differ from the selected device if it was unplugged.
+pub fn current_device() -> String {
+    CURRENT_DEVICE.lock().unwrap().clone()
+}
+
+pub unsafe fn create_wasapi(
+    device: &Device,
+    sample_rate: Option<u32>,
+) -> Result<
+    (
+        IAudioClient,
+        IAudioRenderClient,
+        WAVEFORMATEXTENSIBLE,
+        *mut c_void,
+    ),
+    String,
+> {
+    let client: IAudioClient = device
+        .inner
+        .Activate(ExecutionContext::All)
+        .map_err(|e| format!("{e:?}"))?;
+    let mut format = (client.GetMixFormat().map_err(|e| format!("{e:?}"))? as *const _
+        as *const WAVEFORMATEXTENSIBLE)
+        .read();
 
     if format.Format.nChannels < 2 {
         todo!("Support mono devices.");
@@ -134,7 +208,7 @@ pub unsafe fn create_wasapi(
         format.Format.nAvgBytesPerSec = sample_rate * format.Format.nBlockAlign as u32;
     }
 
-    let (default, _min) = client.GetDevicePeriod().unwrap();
+    let (default, _min) = client.GetDevicePeriod().map_err(|e| format!("{e:?}"))?;
 
     client
         .Initialize(
@@ -147,17 +221,51 @@ pub unsafe fn create_wasapi(
             &format as *const _ as *const WAVEFORMATEX,
             None,
         )
-        .unwrap();
+        .map_err(|e| format!("{e:?}"))?;
 
     //This must be set for some reason.
     let event = CreateEventA(core::ptr::null_mut(), 0, 0, core::ptr::null_mut());
     assert!(!event.is_null());
-    client.SetEventHandle(event as isize).unwrap();
+    client
+        .SetEventHandle(event as isize)
+        .map_err(|e| format!("{e:?}"))?;
+
+    let render_client: IAudioRenderClient = client.GetService().map_err(|e| format!("{e:?}"))?;
+    client.Start().map_err(|e| format!("{e:?}"))?;
 
-    let render_client: IAudioRenderClient = client.GetService().unwrap();
-    client.Start().unwrap();
+    Ok((client, render_client, format, event))
+}
 
-    (client, render_client, format, event)
+//Most shared mode devices use 32-bit float, but not all of them.
+unsafe fn device_converter(format: &WAVEFORMATEXTENSIBLE) -> Converter {
+    let bytes = std::slice::from_raw_parts(
+        format as *const _ as *const u8,
+        std::mem::size_of::<WAVEFORMATEXTENSIBLE>(),
+    );
+    let sample_format = SampleFormat::from_wave_format(bytes).unwrap_or_else(|| {
+        warn!("Unknown device format, using 32-bit float.");
+        SampleFormat::F32
+    });
+    info!("Device format: {:?}", sample_format);
+    Converter::new(sample_format, DITHER)
+}
+
+//Remember the position of long files so they can be resumed later.
+fn update_bookmark(bookmarks: &mut Bookmarks, path: &str, sym: &Symphonia) {
+    if sym.duration() < bookmarks::THRESHOLD {
+        return;
+    }
+
+    //Files that were played to the end start from the beginning next time.
+    if sym.done || sym.elapsed() + Duration::from_secs(10) > sym.duration() {
+        bookmarks.remove(path);
+    } else {
+        bookmarks.set(path, sym.elapsed().as_secs_f32());
+    }
+
+    if let Err(e) = bookmarks.save() {
+        warn!("Failed to save bookmarks: {e}");
+    }
 }
 
 //0.016384MB, no stack overflow here.
@@ -174,6 +282,12 @@ pub fn spawn_audio_threads(device: Device) {
             info!("Spawned decoder thread!");
 
             let mut sym: Option<Symphonia> = None;
+            let mut bookmarks = Bookmarks::new();
+            //Path of the current song and the last saved position.
+            let mut current = String::new();
+            let mut saved = 0.0;
+            let mut cache = Silence::new();
+            let mut detector = silence::Detector::default();
             let mut leftover_packet: Option<SampleBuffer<f32>> = None;
             let mut i = 0;
             let mut finished = true;
@@ -186,7 +300,11 @@ pub fn spawn_audio_threads(device: Device) {
                     Some(Event::Song(new_path, gain)) => {
                         // info!("{} paused: {}", new_path.display(), PAUSED);
                         // info!("Gain: {} prod capacity: {}", gain, prod.capacity());
-                        let s = match Symphonia::new(&new_path) {
+                        if let Some(sym) = &sym {
+                            update_bookmark(&mut bookmarks, &current, sym);
+                        }
+
+                        let mut s = match Symphonia::new(&new_path) {
                             Ok(s) => s,
                             Err(e) => {
                                 gonk_core::log!(
@@ -199,6 +317,20 @@ pub fn spawn_audio_threads(device: Device) {
                             }
                         };
 
+                        current = new_path.to_string_lossy().to_string();
+                        saved = 0.0;
+                        detector = silence::Detector::default();
+
+                        //Resume long files where they were left off.
+                        if s.duration() >= bookmarks::THRESHOLD {
+                            if let Some(pos) = bookmarks.get(&current) {
+                                info!("Resuming {} at {}", current, pos as u32);
+                                s.seek(pos);
+                                saved = pos;
+                                detector.seek();
+                            }
+                        }
+
                         //We don't set the playback state here because it might be delayed.
                         SAMPLE_RATE = Some(s.sample_rate());
                         DURATION = s.duration();
@@ -216,6 +348,10 @@ pub fn spawn_audio_threads(device: Device) {
                     }
                     Some(Event::Stop) => {
                         info!("Stopping playback.");
+                        if let Some(sym) = &sym {
+                            update_bookmark(&mut bookmarks, &current, sym);
+                        }
+
                         //Stop the decoder and remove the extra packet.
                         sym = None;
                         leftover_packet = None;
@@ -234,10 +370,14 @@ pub fn spawn_audio_threads(device: Device) {
                                 PAUSED
                             );
                             sym.seek(pos);
+                            detector.seek();
+                            RESET_DSP = true;
                         }
                     }
                     Some(Event::SeekForward) => {
                         if let Some(sym) = &mut sym {
+                            detector.seek();
+                            RESET_DSP = true;
                             info!(
                                 "Seeking {} / {}",
                                 sym.elapsed().as_secs_f32() + 10.0,
@@ -248,6 +388,8 @@ pub fn spawn_audio_threads(device: Device) {
                     }
                     Some(Event::SeekBackward) => {
                         if let Some(sym) = &mut sym {
+                            detector.seek();
+                            RESET_DSP = true;
                             info!(
                                 "Seeking {} / {}",
                                 sym.elapsed().as_secs_f32() - 10.0,
@@ -286,15 +428,51 @@ pub fn spawn_audio_threads(device: Device) {
                         leftover_packet = None;
                     }
                 } else {
-                    leftover_packet = sym.next_packet();
+                    let packet = sym.next_packet();
                     ELAPSED = sym.elapsed();
 
+                    if (ELAPSED.as_secs_f32() - saved).abs() >= BOOKMARK_INTERVAL {
+                        saved = ELAPSED.as_secs_f32();
+                        update_bookmark(&mut bookmarks, &current, sym);
+                    }
+
+                    if SKIP_SILENCE {
+                        //Jump over silence that was found last time.
+                        if let Some(end) = cache
+                            .get(&current)
+                            .and_then(|regions| silence::region_end(regions, sym.elapsed()))
+                        {
+                            sym.seek(end);
+                            detector.seek();
+                            continue;
+                        }
+
+                        if let Some(p) = &packet {
+                            if detector.skip(p.samples(), sym.elapsed()) {
+                                continue;
+                            }
+                        }
+                    }
+
+                    leftover_packet = packet;
+
                     //It's important that finished is used as a guard.
                     //If next is used it can be changed by a different thread.
                     //This may be an excessive amount of conditions :/
                     if leftover_packet.is_none() && !PAUSED && !finished && !NEXT {
                         finished = true;
                         NEXT = true;
+                        update_bookmark(&mut bookmarks, &current, sym);
+
+                        if SKIP_SILENCE && CACHE_SILENCE && cache.get(&current).is_none() {
+                            if let Some(regions) = detector.finish(sym.duration()) {
+                                cache.set(&current, regions);
+                                if let Err(e) = cache.save() {
+                                    warn!("Failed to save silence: {e}");
+                                }
+                            }
+                        }
+
                         info!("Playback ended.");
                     }
                 }
@@ -306,42 +484,114 @@ pub fn spawn_audio_threads(device: Device) {
             init_com();
             set_pro_audio_thread();
 
-            let (mut audio, mut render, mut format, mut event) = create_wasapi(&device, None);
+            let mut device = device;
+            let (mut audio, mut render, mut format, mut event) = match create_wasapi(&device, None)
+            {
+                Ok(wasapi) => wasapi,
+                Err(e) => {
+                    gonk_core::log!("Failed to use {}: {e}", device.name);
+                    loop {
+                        match fallback_device(None) {
+                            Ok((fallback, wasapi)) => {
+                                device = fallback;
+                                break wasapi;
+                            }
+                            Err(e) => {
+                                warn!("No output device available: {e}");
+                                thread::sleep(DEVICE_TIMEOUT_DURATION);
+                            }
+                        }
+                    }
+                }
+            };
+            set_current_device(&device.name);
+
             let mut block_align = format.Format.nBlockAlign as u32;
+            let mut converter = device_converter(&format);
             let mut sample_rate = format.Format.nSamplesPerSec;
             let mut gain = 0.5;
+            let mut buffer: Vec<f32> = Vec::new();
+            let mut chain = Chain::default();
+            //The output device was unplugged or disabled.
+            let mut lost = false;
 
             loop {
                 //Block until the output device is ready for new samples.
-                if WaitForSingleObject(event, u32::MAX) != WAIT_OBJECT_0 {
-                    unreachable!();
-                }
-
-                if PAUSED {
+                //This times out so removed devices can still be detected.
+                let _ = WaitForSingleObject(event, DEVICE_TIMEOUT);
+
+                if lost {
+                    //Move playback to the default device, unless a device was picked since.
+                    match fallback_device(Some(sample_rate)) {
+                        Ok((fallback, wasapi)) => {
+                            gonk_core::log!(
+                                "Lost output device {}, switched to {}.",
+                                device.name,
+                                fallback.name
+                            );
+                            (audio, render, format, event) = wasapi;
+                            block_align = format.Format.nBlockAlign as u32;
+                            converter = device_converter(&format);
+                            device = fallback;
+                            set_current_device(&device.name);
+                            lost = false;
+                        }
+                        Err(e) => {
+                            warn!("No output device available: {e}");
+                            thread::sleep(DEVICE_TIMEOUT_DURATION);
+                        }
+                    }
                     continue;
                 }
 
-                if let Some(device) = OUTPUT_DEVICE.take() {
-                    info!("Changing output device to: {}", device.name);
+                //Devices can be changed while paused.
+                if let Some(new_device) = OUTPUT_DEVICE.take() {
+                    info!("Changing output device to: {}", new_device.name);
                     //Set the new audio device.
-                    audio.Stop().unwrap();
-                    (audio, render, format, event) = create_wasapi(&device, Some(sample_rate));
-                    //Different devices have different block alignments.
-                    block_align = format.Format.nBlockAlign as u32;
+                    let _ = audio.Stop();
+                    match create_wasapi(&new_device, Some(sample_rate)) {
+                        Ok(wasapi) => {
+                            (audio, render, format, event) = wasapi;
+                            //Different devices have different block alignments.
+                            block_align = format.Format.nBlockAlign as u32;
+                            converter = device_converter(&format);
+                            device = new_device;
+                            set_current_device(&device.name);
+                            gonk_core::log!("Changed output device to {}.", device.name);
+                        }
+                        Err(e) => {
+                            gonk_core::log!("Failed to use {}: {e}", new_device.name);
+                            let _ = audio.Start();
+                        }
+                    }
+                }
+
+                if PAUSED {
+                    continue;
                 }
 
                 if let Some(sr) = SAMPLE_RATE {
                     if sr != sample_rate {
                         info!("Changing sample rate to {}", sr);
-                        let device = OUTPUT_DEVICE.as_ref().unwrap_or(&device);
                         sample_rate = sr;
 
                         //Set the new sample rate.
-                        audio.Stop().unwrap();
-                        (audio, render, format, event) = create_wasapi(device, Some(sample_rate));
-                        //Doesn't need to be set since it's the same device.
-                        //I just did this to avoid any issues.
-                        block_align = format.Format.nBlockAlign as u32;
+                        let _ = audio.Stop();
+                        match create_wasapi(&device, Some(sample_rate)) {
+                            Ok(wasapi) => {
+                                (audio, render, format, event) = wasapi;
+                                //Doesn't need to be set since it's the same device.
+                                //I just did this to avoid any issues.
+                                block_align = format.Format.nBlockAlign as u32;
+                                converter = device_converter(&format);
+                            }
+                            Err(e) => {
+                                warn!("Failed to change sample rate: {e}");
+                                PAUSED = true;
+                                lost = true;
+                                continue;
+                            }
+                        }
                     }
                 }
 
@@ -352,12 +602,22 @@ pub fn spawn_audio_threads(device: Device) {
                     //Make sure there are no old samples before dramatically increasing the volume.
                     //Without this there were some serious jumps in volume when skipping songs.
                     cons.clear();
-                    debug_assert!(cons.is_empty())
+                    debug_assert!(cons.is_empty());
+
+                    //Effects shouldn't carry over between songs.
+                    chain.reset();
                 }
 
-                //Sample-rate probably changed if this fails.
-                let padding = audio.GetCurrentPadding().unwrap();
-                let buffer_size = audio.GetBufferSize().unwrap();
+                //The device was removed if this fails.
+                let (padding, buffer_size) =
+                    match (audio.GetCurrentPadding(), audio.GetBufferSize()) {
+                        (Ok(padding), Ok(buffer_size)) => (padding, buffer_size),
+                        _ => {
+                            PAUSED = true;
+                            lost = true;
+                            continue;
+                        }
+                    };
 
                 let n_frames = buffer_size - 1 - padding;
                 debug_assert!(n_frames < buffer_size - padding);
@@ -368,23 +628,52 @@ pub fn spawn_audio_threads(device: Device) {
                     continue;
                 }
 
-                let b = render.GetBuffer(n_frames).unwrap();
+                let Ok(b) = render.GetBuffer(n_frames) else {
+                    PAUSED = true;
+                    lost = true;
+                    continue;
+                };
                 let output = std::slice::from_raw_parts_mut(b, size);
                 let channels = format.Format.nChannels as usize;
-                let volume = VOLUME * gain;
+                //Only the first two channels are used.
+                let used_channels = channels.min(2);
+                let volume = VOLUME * gain * FADE;
+
+                buffer.clear();
+                buffer.extend(cons.pop_iter().take(n_frames as usize * used_channels));
+                buffer.resize(n_frames as usize * used_channels, 0.0);
 
-                let mut iter = cons.pop_iter();
-                for bytes in output.chunks_mut(std::mem::size_of::<f32>() * channels) {
-                    let sample = iter.next().unwrap_or_default();
-                    bytes[0..4].copy_from_slice(&(sample * volume).to_le_bytes());
+                for sample in &mut buffer {
+                    *sample *= volume;
+                }
 
-                    if channels > 1 {
-                        let sample = iter.next().unwrap_or_default();
-                        bytes[4..8].copy_from_slice(&(sample * volume).to_le_bytes());
+                dsp::apply_edits(&mut chain);
+                if RESET_DSP {
+                    RESET_DSP = false;
+                    chain.reset();
+                }
+                chain.process(&mut buffer, sample_rate, used_channels);
+
+                if buffer.iter().any(|sample| sample.abs() > 1.0) {
+                    CLIPPED = Some(Instant::now());
+                }
+
+                //Convert to the sample format of the device.
+                converter.dither = DITHER;
+                let bytes = converter.format.bytes();
+                for (frame_bytes, frame) in output
+                    .chunks_mut(block_align as usize)
+                    .zip(buffer.chunks(used_channels))
+                {
+                    for (i, sample) in frame.iter().enumerate() {
+                        converter.write(*sample, &mut frame_bytes[i * bytes..(i + 1) * bytes]);
                     }
                 }
 
-                render.ReleaseBuffer(n_frames, 0).unwrap();
+                if render.ReleaseBuffer(n_frames, 0).is_err() {
+                    PAUSED = true;
+                    lost = true;
+                }
             }
         });
     }
@@ -424,6 +713,67 @@ pub fn volume_down() {
     }
 }
 
+///Scale the volume without changing it, used for fading out.
+pub fn set_fade(fade: f32) {
+    unsafe { FADE = fade.clamp(0.0, 1.0) };
+}
+
+///Amplify or reduce the volume before the limiter, in decibels.
+pub fn set_preamp(db: f32) {
+    dsp::edit(move |chain| chain.replace(Box::new(Preamp::new(db))));
+}
+
+pub fn set_limiter(enabled: bool) {
+    dsp::edit(move |chain| chain.set_enabled("limiter", enabled));
+}
+
+///Load songs into memory in the background, for slow or network storage.
+pub fn set_read_ahead(enabled: bool) {
+    unsafe { READ_AHEAD = enabled };
+}
+
+///Start loading a song that will be played next, only used with read-ahead.
+pub fn preload(song: &Song) {
+    if unsafe { READ_AHEAD } {
+        read_ahead::preload(PathBuf::from(&song.path));
+    }
+}
+
+///-1.0 is fully left, 1.0 is fully right.
+pub fn set_balance(balance: f32) {
+    dsp::edit(move |chain| chain.replace(Box::new(Balance::new(balance))));
+}
+
+pub fn set_mono(enabled: bool) {
+    dsp::edit(move |chain| chain.set_enabled("mono", enabled));
+}
+
+pub fn set_swap_channels(enabled: bool) {
+    dsp::edit(move |chain| chain.set_enabled("swap", enabled));
+}
+
+pub fn set_crossfeed(enabled: bool) {
+    dsp::edit(move |chain| chain.set_enabled("crossfeed", enabled));
+}
+
+///Add TPDF dither when the output uses integer samples.
+pub fn set_dither(enabled: bool) {
+    unsafe { DITHER = enabled };
+}
+
+//Keep the indicator on screen long enough to be seen.
+const INDICATOR_HOLD: Duration = Duration::from_millis(500);
+
+///The limiter has reduced the volume recently.
+pub fn is_limiting() -> bool {
+    unsafe { LIMITED.is_some_and(|instant| instant.elapsed() < INDICATOR_HOLD) }
+}
+
+///The output has clipped recently, only possible when the limiter is disabled.
+pub fn is_clipping() -> bool {
+    unsafe { CLIPPED.is_some_and(|instant| instant.elapsed() < INDICATOR_HOLD) }
+}
+
 pub fn seek(pos: f32) {
     unsafe {
         EVENTS.push(Event::Seek(pos));
@@ -448,28 +798,36 @@ pub fn play_path<P: AsRef<Path>>(path: P) {
     }
 }
 
+//Songs without replay gain are played at half volume.
+fn song_gain(song: &Song) -> f32 {
+    if song.gain == 0.0 {
+        0.5
+    } else {
+        song.gain
+    }
+}
+
 pub fn play_song(song: &Song) {
     unsafe {
         PAUSED = false;
         ELAPSED = Duration::from_secs(0);
-        EVENTS.push(Event::Song(
-            PathBuf::from(&song.path),
-            if song.gain == 0.0 { 0.5 } else { song.gain },
-        ));
+        EVENTS.push(Event::Song(PathBuf::from(&song.path), song_gain(song)));
     }
 }
 
-pub fn set_output_device(device: &str) {
+///Change the output device, fails if the device is not connected.
+pub fn set_output_device(device: &str) -> Result<(), String> {
     let d = devices();
-    unsafe {
-        match d.iter().find(|d| d.name == device) {
-            Some(device) => OUTPUT_DEVICE = Some(device.clone()),
-            None => panic!(
-                "Could not find {} in {:?}",
-                device,
-                d.into_iter().map(|d| d.name).collect::<Vec<String>>()
-            ),
+    match d.iter().find(|d| d.name == device) {
+        Some(device) => {
+            unsafe { OUTPUT_DEVICE = Some(device.clone()) };
+            Ok(())
         }
+        None => Err(format!(
+            "Could not find {} in {:?}",
+            device,
+            d.into_iter().map(|d| d.name).collect::<Vec<String>>()
+        )),
     }
 }
 
@@ -520,6 +878,16 @@ pub fn clear_except_playing(songs: &mut Index<Song>) {
     }
 }
 
+///Skip leading silence and shorten long gaps.
+pub fn set_skip_silence(skip: bool) {
+    unsafe { SKIP_SILENCE = skip };
+}
+
+///Remember where the silence is so it can be skipped instantly next time.
+pub fn set_cache_silence(cache: bool) {
+    unsafe { CACHE_SILENCE = cache };
+}
+
 pub fn is_paused() -> bool {
     unsafe { PAUSED }
 }
@@ -0,0 +1,165 @@
+//! Look-ahead peak limiter.
+//!
+//! Output is delayed by `LOOKAHEAD` so the gain can be lowered before a peak arrives.
+//! The required gain is held for the look-ahead window then smoothed with a
+//! box filter of the same length, this guarantees the peak never goes over the threshold.
+use crate::{dsp::Dsp, LIMITED};
+use std::{collections::VecDeque, time::Instant};
+
+//-0.3 dBFS
+const THRESHOLD: f32 = 0.966;
+//Seconds
+const LOOKAHEAD: f32 = 0.005;
+const RELEASE: f32 = 0.1;
+
+pub struct Limiter {
+    sample_rate: u32,
+    channels: usize,
+    lookahead: usize,
+    release: f32,
+    //Delayed input samples.
+    delay: VecDeque<f32>,
+    //(frame index, gain) with increasing gain, used for the sliding minimum.
+    min: VecDeque<(u64, f32)>,
+    //The last `lookahead` held gains.
+    hold: VecDeque<f32>,
+    sum: f64,
+    envelope: f32,
+    index: u64,
+    ///Lowest gain applied to the last block.
+    pub reduction: f32,
+}
+
+impl Limiter {
+    pub fn new() -> Self {
+        Self {
+            sample_rate: 0,
+            channels: 0,
+            lookahead: 1,
+            release: 0.0,
+            delay: VecDeque::new(),
+            min: VecDeque::new(),
+            hold: VecDeque::new(),
+            sum: 0.0,
+            envelope: 1.0,
+            index: 0,
+            reduction: 1.0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.delay = vec![0.0; (self.lookahead - 1) * self.channels].into();
+        self.min.clear();
+        self.hold = vec![1.0; self.lookahead].into();
+        self.sum = self.lookahead as f64;
+        self.envelope = 1.0;
+        self.reduction = 1.0;
+    }
+
+    pub fn process(&mut self, samples: &mut [f32], sample_rate: u32, channels: usize) {
+        if sample_rate != self.sample_rate || channels != self.channels {
+            self.sample_rate = sample_rate;
+            self.channels = channels;
+            self.lookahead = ((sample_rate as f32 * LOOKAHEAD) as usize).max(1);
+            self.release = (-1.0 / (RELEASE * sample_rate as f32)).exp();
+            self.reset();
+        }
+
+        let mut reduction = 1.0f32;
+
+        for frame in samples.chunks_exact_mut(channels) {
+            let peak = frame.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+            let required = if peak > THRESHOLD {
+                THRESHOLD / peak
+            } else {
+                1.0
+            };
+
+            //Sliding minimum of the required gain.
+            while let Some((_, gain)) = self.min.back() {
+                if *gain >= required {
+                    self.min.pop_back();
+                } else {
+                    break;
+                }
+            }
+            self.min.push_back((self.index, required));
+            while let Some((i, _)) = self.min.front() {
+                if *i + self.lookahead as u64 <= self.index {
+                    self.min.pop_front();
+                } else {
+                    break;
+                }
+            }
+            self.index += 1;
+            let held = self.min.front().map(|(_, gain)| *gain).unwrap_or(1.0);
+
+            //Smooth the held gain so it ramps down into the peak.
+            self.sum += held as f64 - self.hold.pop_front().unwrap_or(1.0) as f64;
+            self.hold.push_back(held);
+            let target = (self.sum / self.lookahead as f64) as f32;
+
+            //Attack instantly, release slowly.
+            self.envelope = if target < self.envelope {
+                target
+            } else {
+                target + (self.envelope - target) * self.release
+            };
+
+            self.delay.extend(frame.iter().copied());
+            for sample in frame.iter_mut() {
+                *sample = self.delay.pop_front().unwrap_or_default() * self.envelope;
+            }
+
+            reduction = reduction.min(self.envelope);
+        }
+
+        self.reduction = reduction;
+    }
+}
+
+impl Dsp for Limiter {
+    fn name(&self) -> &str {
+        "limiter"
+    }
+    fn process(&mut self, samples: &mut [f32], sample_rate: u32, channels: usize) {
+        Limiter::process(self, samples, sample_rate, channels);
+        if self.reduction < 1.0 {
+            unsafe { LIMITED = Some(Instant::now()) };
+        }
+    }
+    fn reset(&mut self) {
+        Limiter::reset(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limit() {
+        let mut limiter = Limiter::new();
+        let mut samples: Vec<f32> = (0..48000).map(|i| (i as f32 * 0.05).sin() * 2.0).collect();
+
+        for block in samples.chunks_mut(960) {
+            limiter.process(block, 48000, 2);
+        }
+
+        assert!(samples.iter().all(|s| s.abs() <= THRESHOLD + 0.0001));
+        assert!(limiter.reduction < 1.0);
+    }
+
+    #[test]
+    fn transparent() {
+        let mut limiter = Limiter::new();
+        let input: Vec<f32> = (0..4800).map(|i| (i as f32 * 0.05).sin() * 0.5).collect();
+        let mut output = input.clone();
+        limiter.process(&mut output, 48000, 2);
+
+        //Quiet audio is only delayed.
+        let delay = (limiter.lookahead - 1) * 2;
+        assert_eq!(&output[delay..], &input[..input.len() - delay]);
+        assert_eq!(limiter.reduction, 1.0);
+    }
+}
@@ -0,0 +1,228 @@
+//! Read-ahead buffering for slow or network storage.
+//!
+//! Files are copied into memory by a background thread while they're being decoded.
+//! Anything the loader hasn't reached yet is read from a second handle to the file,
+//! so seeking ahead doesn't wait for the whole file to load.
+use std::{
+    fs::File,
+    io::{self, ErrorKind, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+};
+use symphonia::core::io::MediaSource;
+
+//Larger files are read directly from storage.
+const MAX_SIZE: u64 = 512 * 1024 * 1024;
+const CHUNK_SIZE: usize = 256 * 1024;
+
+//The next song in the queue.
+static PRELOAD: Mutex<Option<(PathBuf, Loader)>> = Mutex::new(None);
+
+#[derive(Default)]
+struct State {
+    data: Vec<u8>,
+    finished: bool,
+    error: Option<ErrorKind>,
+}
+
+#[derive(Clone)]
+struct Loader {
+    state: Arc<Mutex<State>>,
+    len: u64,
+}
+
+///Start copying the file into memory, returns `None` if it's too large.
+fn load(path: &Path) -> io::Result<Option<Loader>> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    if len > MAX_SIZE {
+        return Ok(None);
+    }
+
+    let state = Arc::new(Mutex::new(State::default()));
+    state.lock().unwrap().data.reserve_exact(len as usize);
+    let loader = Arc::clone(&state);
+
+    thread::spawn(move || {
+        let mut chunk = vec![0; CHUNK_SIZE];
+        loop {
+            //Nobody is going to read the rest of the file.
+            if Arc::strong_count(&loader) == 1 {
+                break;
+            }
+
+            let result = file.read(&mut chunk);
+            let mut state = loader.lock().unwrap();
+            match result {
+                Ok(0) => state.finished = true,
+                Ok(n) => state.data.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    state.error = Some(e.kind());
+                    state.finished = true;
+                }
+            }
+            if state.finished {
+                break;
+            }
+        }
+    });
+
+    Ok(Some(Loader { state, len }))
+}
+
+///Open a file for decoding, using the preloaded copy if there is one.
+pub fn open(path: &Path) -> io::Result<Box<dyn MediaSource>> {
+    //A preload for another song is dropped, which stops its loader.
+    let preloaded = match PRELOAD.lock().unwrap().take() {
+        Some((p, loader)) if p == path => Some(loader),
+        _ => None,
+    };
+
+    let loader = match preloaded {
+        Some(loader) => Some(loader),
+        None => load(path)?,
+    };
+
+    match loader {
+        Some(Loader { state, len }) => Ok(Box::new(ReadAhead {
+            state,
+            len,
+            pos: 0,
+            path: path.to_path_buf(),
+            file: None,
+        })),
+        None => Ok(Box::new(File::open(path)?)),
+    }
+}
+
+///Start loading a file that will be played soon.
+pub fn preload(path: PathBuf) {
+    thread::spawn(move || {
+        if let Ok(Some(loader)) = load(&path) {
+            *PRELOAD.lock().unwrap() = Some((path, loader));
+        }
+    });
+}
+
+pub struct ReadAhead {
+    state: Arc<Mutex<State>>,
+    len: u64,
+    pos: u64,
+    path: PathBuf,
+    //Opened the first time the decoder gets ahead of the loader.
+    file: Option<File>,
+}
+
+impl ReadAhead {
+    fn read_file(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.file.is_none() {
+            self.file = Some(File::open(&self.path)?);
+        }
+        let file = self.file.as_mut().unwrap();
+        file.seek(SeekFrom::Start(self.pos))?;
+        let n = file.read(buf)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Read for ReadAhead {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let state = self.state.lock().unwrap();
+        if state.data.len() as u64 <= self.pos && !state.finished {
+            drop(state);
+            return self.read_file(buf);
+        }
+
+        let start = (self.pos as usize).min(state.data.len());
+        if start == state.data.len() {
+            if let Some(kind) = state.error {
+                return Err(kind.into());
+            }
+        }
+
+        let n = buf.len().min(state.data.len() - start);
+        buf[..n].copy_from_slice(&state.data[start..start + n]);
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for ReadAhead {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+        };
+        match pos {
+            Some(pos) => {
+                self.pos = pos;
+                Ok(pos)
+            }
+            None => Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+impl MediaSource for ReadAhead {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+    fn byte_len(&self) -> Option<u64> {
+        Some(self.len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_ahead() {
+        let path = std::env::temp_dir().join("gonk_read_ahead.bin");
+        let bytes: Vec<u8> = (0..CHUNK_SIZE * 3).map(|i| i as u8).collect();
+        std::fs::write(&path, &bytes).unwrap();
+
+        let mut source = open(&path).unwrap();
+        assert_eq!(source.byte_len(), Some(bytes.len() as u64));
+
+        let mut output = Vec::new();
+        source.read_to_end(&mut output).unwrap();
+        assert_eq!(output, bytes);
+
+        source.seek(SeekFrom::End(-10)).unwrap();
+        let mut output = [0; 20];
+        assert_eq!(source.read(&mut output).unwrap(), 10);
+        assert_eq!(output[..10], bytes[bytes.len() - 10..]);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn read_past_loader() {
+        let path = std::env::temp_dir().join("gonk_read_past_loader.bin");
+        let bytes: Vec<u8> = (0..100).collect();
+        std::fs::write(&path, &bytes).unwrap();
+
+        //Nothing has been loaded yet.
+        let mut source = ReadAhead {
+            state: Arc::default(),
+            len: bytes.len() as u64,
+            pos: 0,
+            path: path.clone(),
+            file: None,
+        };
+        source.seek(SeekFrom::Start(90)).unwrap();
+        let mut output = Vec::new();
+        source.read_to_end(&mut output).unwrap();
+        assert_eq!(output, bytes[90..]);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,182 @@
+//! Offline rendering
+//!
+//! Runs the same decode -> gain -> resample -> DSP chain as playback
+//! but writes the output to a WAV file instead of an output device.
+use crate::{
+    decoder::Symphonia,
+    dsp::Chain,
+    format::{Converter, SampleFormat},
+    resample::Resampler,
+    song_gain, DITHER,
+};
+use gonk_core::Song;
+use std::{
+    error::Error,
+    fs::File,
+    io::{self, BufWriter, Seek, SeekFrom, Write},
+    path::Path,
+    time::Duration,
+};
+
+//Output is always stereo, same as the WASAPI thread.
+const CHANNELS: usize = 2;
+
+//The RIFF size is 32 bits and includes the 36 bytes of header after it.
+const MAX_DATA_LEN: u32 = u32::MAX - 36;
+
+///WAV file with float or integer samples.
+pub struct WavWriter {
+    writer: BufWriter<File>,
+    converter: Converter,
+    bytes: Vec<u8>,
+    data_len: u32,
+}
+
+impl WavWriter {
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        sample_rate: u32,
+        channels: u16,
+        format: SampleFormat,
+    ) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let block_align = channels * format.bytes() as u16;
+
+        writer.write_all(b"RIFF")?;
+        //File size, written in `finish`.
+        writer.write_all(&0u32.to_le_bytes())?;
+        writer.write_all(b"WAVE")?;
+
+        writer.write_all(b"fmt ")?;
+        writer.write_all(&16u32.to_le_bytes())?;
+        writer.write_all(&format.format_tag().to_le_bytes())?;
+        writer.write_all(&channels.to_le_bytes())?;
+        writer.write_all(&sample_rate.to_le_bytes())?;
+        writer.write_all(&(sample_rate * block_align as u32).to_le_bytes())?;
+        writer.write_all(&block_align.to_le_bytes())?;
+        writer.write_all(&format.bits().to_le_bytes())?;
+
+        writer.write_all(b"data")?;
+        //Data size, written in `finish`.
+        writer.write_all(&0u32.to_le_bytes())?;
+
+        Ok(Self {
+            writer,
+            converter: Converter::new(format, unsafe { DITHER }),
+            bytes: vec![0; format.bytes()],
+            data_len: 0,
+        })
+    }
+
+    ///Fails without writing anything if the file would be larger than 4 GiB.
+    pub fn write(&mut self, samples: &[f32]) -> io::Result<()> {
+        self.data_len = u32::try_from(samples.len() * self.bytes.len())
+            .ok()
+            .and_then(|len| self.data_len.checked_add(len))
+            .filter(|len| *len <= MAX_DATA_LEN)
+            .ok_or_else(|| io::Error::other("WAV files can't be larger than 4 GiB."))?;
+
+        for sample in samples {
+            self.converter.write(*sample, &mut self.bytes);
+            self.writer.write_all(&self.bytes)?;
+        }
+        Ok(())
+    }
+
+    ///Write the chunk sizes and flush the file.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.writer.seek(SeekFrom::Start(4))?;
+        self.writer.write_all(&(36 + self.data_len).to_le_bytes())?;
+        self.writer.seek(SeekFrom::Start(40))?;
+        self.writer.write_all(&self.data_len.to_le_bytes())?;
+        self.writer.flush()
+    }
+}
+
+///Convert interleaved samples with any number of channels to stereo.
+fn stereo(samples: &[f32], channels: usize, output: &mut Vec<f32>) {
+    match channels {
+        0 => {}
+        1 => {
+            for sample in samples {
+                output.push(*sample);
+                output.push(*sample);
+            }
+        }
+        _ => {
+            for frame in samples.chunks_exact(channels) {
+                output.push(frame[0]);
+                output.push(frame[1]);
+            }
+        }
+    }
+}
+
+///Decode the songs in order and write them to a single WAV file as fast as possible.
+///
+///Everything is resampled to the sample rate of the first song.
+///Integer formats are dithered if dithering is enabled.
+///Returns the length of the rendered audio.
+///
+///Use `dsp::apply_edits` on a new chain to render with the playback effects,
+///this shouldn't be done while the player is running.
+pub fn render<P: AsRef<Path>>(
+    songs: &[Song],
+    output: P,
+    format: SampleFormat,
+    chain: &mut Chain,
+) -> Result<Duration, Box<dyn Error>> {
+    let mut writer: Option<WavWriter> = None;
+    let mut sample_rate = 0;
+    let mut frames = 0;
+
+    let mut buffer = Vec::new();
+    let mut resampled = Vec::new();
+
+    for song in songs {
+        let mut sym = Symphonia::new(&song.path)
+            .map_err(|err| format!("Failed to render: {}, Error: {err}", song.path))?;
+
+        if writer.is_none() {
+            sample_rate = sym.sample_rate();
+            writer = Some(WavWriter::create(
+                &output,
+                sample_rate,
+                CHANNELS as u16,
+                format,
+            )?);
+        }
+        let Some(writer) = &mut writer else {
+            unreachable!();
+        };
+
+        let channels = sym.channels();
+        let gain = song_gain(song);
+        let mut resampler = Resampler::new(sym.sample_rate(), sample_rate, CHANNELS);
+        chain.reset();
+
+        while let Some(packet) = sym.next_packet() {
+            buffer.clear();
+            stereo(packet.samples(), channels, &mut buffer);
+
+            for sample in &mut buffer {
+                *sample *= gain;
+            }
+
+            resampled.clear();
+            resampler.process(&buffer, &mut resampled);
+
+            chain.process(&mut resampled, sample_rate, CHANNELS);
+
+            writer.write(&resampled)?;
+            frames += resampled.len() / CHANNELS;
+        }
+    }
+
+    match writer {
+        Some(writer) => writer.finish()?,
+        None => return Err("Nothing to render.")?,
+    }
+
+    Ok(Duration::from_secs_f64(frames as f64 / sample_rate as f64))
+}
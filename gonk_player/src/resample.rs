@@ -0,0 +1,89 @@
+//! Linear resampler for interleaved audio.
+//!
+//! WASAPI does sample rate conversion for us during playback.
+//! This is only used when there is no device, like offline rendering.
+pub struct Resampler {
+    from: u32,
+    to: u32,
+    channels: usize,
+    //Position of the next output frame, relative to `prev`.
+    pos: f64,
+    //Last frame of the previous block.
+    prev: Vec<f32>,
+}
+
+impl Resampler {
+    pub fn new(from: u32, to: u32, channels: usize) -> Self {
+        Self {
+            from,
+            to,
+            channels,
+            pos: 0.0,
+            prev: vec![0.0; channels],
+        }
+    }
+
+    ///Resample `input` and append the result to `output`.
+    pub fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        if self.from == self.to {
+            return output.extend_from_slice(input);
+        }
+
+        let channels = self.channels;
+        let step = self.from as f64 / self.to as f64;
+        let frames = input.len() / channels;
+
+        //Frame 0 is the last frame of the previous block, frame n is input[n - 1].
+        while self.pos < frames as f64 {
+            let i = self.pos.floor() as usize;
+            let t = (self.pos - i as f64) as f32;
+
+            for c in 0..channels {
+                let a = if i == 0 {
+                    self.prev[c]
+                } else {
+                    input[(i - 1) * channels + c]
+                };
+                let b = input[i * channels + c];
+                output.push(a + (b - a) * t);
+            }
+
+            self.pos += step;
+        }
+
+        self.pos -= frames as f64;
+
+        if frames > 0 {
+            self.prev
+                .copy_from_slice(&input[(frames - 1) * channels..frames * channels]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passthrough() {
+        let mut resampler = Resampler::new(44100, 44100, 2);
+        let mut output = Vec::new();
+        resampler.process(&[0.1, 0.2, 0.3, 0.4], &mut output);
+        assert_eq!(output, [0.1, 0.2, 0.3, 0.4]);
+    }
+
+    #[test]
+    fn length() {
+        let input = vec![0.5; 44100 * 2];
+        let mut output = Vec::new();
+        let mut resampler = Resampler::new(44100, 48000, 2);
+
+        //Split the input to make sure blocks are stitched together.
+        for chunk in input.chunks(1024) {
+            resampler.process(chunk, &mut output);
+        }
+
+        let frames = output.len() / 2;
+        assert!((47999..=48001).contains(&frames), "{frames}");
+    }
+}
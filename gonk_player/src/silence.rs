@@ -0,0 +1,107 @@
+//! Silence detection for skip silence mode.
+//!
+//! Leading silence is removed completely.
+//! Internal and trailing silence is shortened to `KEEP`.
+use std::time::Duration;
+
+//-60 dBFS
+const THRESHOLD: f32 = 0.001;
+const KEEP: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Default)]
+pub struct Detector {
+    //Start of the current run of silence.
+    start: Option<Duration>,
+    //Start of the part of the current run that is being skipped.
+    skip_start: Option<Duration>,
+    //Set once a packet that isn't silent has been found.
+    started: bool,
+    //Set when the user seeks, the regions will be incomplete.
+    incomplete: bool,
+    pub regions: Vec<(f32, f32)>,
+}
+
+impl Detector {
+    ///Returns true if the packet should be skipped.
+    pub fn skip(&mut self, samples: &[f32], elapsed: Duration) -> bool {
+        let peak = samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+
+        if peak >= THRESHOLD {
+            if let Some(skip_start) = self.skip_start.take() {
+                self.regions
+                    .push((skip_start.as_secs_f32(), elapsed.as_secs_f32()));
+            }
+            self.start = None;
+            self.started = true;
+            return false;
+        }
+
+        let start = *self.start.get_or_insert(elapsed);
+        let keep = if self.started { KEEP } else { Duration::ZERO };
+
+        if elapsed.saturating_sub(start) >= keep {
+            self.skip_start.get_or_insert(elapsed);
+            true
+        } else {
+            false
+        }
+    }
+
+    ///The current run of silence is no longer valid after a seek.
+    pub fn seek(&mut self) {
+        self.start = None;
+        self.skip_start = None;
+        self.started = true;
+        self.incomplete = true;
+    }
+
+    ///Close the last region, returns `None` if the song wasn't played from start to finish.
+    pub fn finish(&mut self, duration: Duration) -> Option<Vec<(f32, f32)>> {
+        if let Some(skip_start) = self.skip_start.take() {
+            self.regions
+                .push((skip_start.as_secs_f32(), duration.as_secs_f32()));
+        }
+
+        if self.incomplete {
+            None
+        } else {
+            Some(std::mem::take(&mut self.regions))
+        }
+    }
+}
+
+///Find the end of the cached region that contains `elapsed`.
+pub fn region_end(regions: &[(f32, f32)], elapsed: Duration) -> Option<f32> {
+    let elapsed = elapsed.as_secs_f32();
+    regions
+        .iter()
+        .find(|(start, end)| elapsed >= *start && elapsed + KEEP.as_secs_f32() < *end)
+        .map(|(_, end)| *end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detector() {
+        let silent = [0.0; 64];
+        let loud = [0.5; 64];
+        let ms = Duration::from_millis;
+        let mut detector = Detector::default();
+
+        //Leading silence is skipped straight away.
+        assert!(detector.skip(&silent, ms(0)));
+        assert!(detector.skip(&silent, ms(500)));
+        assert!(!detector.skip(&loud, ms(1000)));
+
+        //Internal silence is kept for one second.
+        assert!(!detector.skip(&silent, ms(2000)));
+        assert!(!detector.skip(&silent, ms(2500)));
+        assert!(detector.skip(&silent, ms(3000)));
+        assert!(!detector.skip(&loud, ms(4000)));
+
+        let regions = detector.finish(ms(5000)).unwrap();
+        assert_eq!(regions, [(0.0, 1.0), (3.0, 4.0)]);
+    }
+}